@@ -0,0 +1,138 @@
+//! achievements defined in the config, unlocked once per user and shown as
+//! badges in `/stats`. conditions are checked whenever a member's message
+//! counters or xp change
+
+use super::{i18n, messages, Handler, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::{error, info};
+use serenity::model::{channel::Message, id::ChannelId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Messages(u64),
+    /// consecutive days with a message
+    Streak(u32),
+    /// leaderboard position at or above
+    Top(usize),
+    Xp(f64),
+    /// messages sent in one channel
+    Channel(ChannelId, u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Achievement {
+    pub id: String,
+    pub condition: Condition,
+    pub badge: String,
+    pub name: String,
+}
+
+impl Achievement {
+    fn reached(&self, meta: &XPMeta, position: Option<usize>) -> bool {
+        match self.condition {
+            Condition::Messages(n) => meta.messages >= n,
+            Condition::Streak(n) => meta.streak >= n,
+            Condition::Top(n) => position.map(|p| p <= n).unwrap_or(false),
+            Condition::Xp(n) => meta.xp >= n,
+            Condition::Channel(channel, n) => {
+                meta.channel_messages.get(&channel.0).cloned().unwrap_or(0) >= n
+            }
+        }
+    }
+}
+
+/// bumps the message counters for a message sent in `channel`
+pub fn track(meta: &mut XPMeta, channel: ChannelId, achievements: &[Achievement], now: DateTime<Utc>) {
+    meta.messages += 1;
+    let today = now.date().naive_utc();
+    match meta.streak_day {
+        Some(day) if day == today => {}
+        Some(day) if day.succ() == today => meta.streak += 1,
+        _ => meta.streak = 1,
+    }
+    meta.streak_day = Some(today);
+    let watched = achievements.iter().any(|a| match a.condition {
+        Condition::Channel(c, _) => c == channel,
+        _ => false,
+    });
+    if watched {
+        *meta.channel_messages.entry(channel.0).or_insert(0) += 1;
+    }
+}
+
+/// marks newly reached achievements as unlocked and returns them
+fn unlock<'a>(achievements: &'a [Achievement], meta: &mut XPMeta, position: Option<usize>) -> Vec<&'a Achievement> {
+    let new: Vec<&Achievement> = achievements
+        .iter()
+        .filter(|a| !meta.badges.contains(&a.id) && a.reached(meta, position))
+        .collect();
+    meta.badges.extend(new.iter().map(|a| a.id.clone()));
+    new
+}
+
+/// unlocks whatever the author of `msg` has reached and announces it once.
+/// top-n badges are only checked when `gained` xp could have moved them up
+pub fn check(state: &State, msg: &Message, gained: bool) {
+    let achievements = &state.settings.achievements;
+    if achievements.is_empty() {
+        return;
+    }
+    let mut meta = match Handler::get_user(&state.db, msg.author.id) {
+        Ok(meta) => meta,
+        Err(_) => return,
+    };
+    // the leaderboard scan is only worth it if the member moved and a top-n
+    // badge is still locked
+    let wants_position = gained && achievements.iter().any(|a| match a.condition {
+        Condition::Top(_) => !meta.badges.contains(&a.id),
+        _ => false,
+    });
+    let position = if wants_position {
+        messages::position(state, meta.xp)
+    } else {
+        None
+    };
+    let unlocked = unlock(achievements, &mut meta, position);
+    if unlocked.is_empty() {
+        return;
+    }
+    let description = unlocked
+        .iter()
+        .map(|a| format!("{} **{}**", a.badge, a.name))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if let Err(why) = Handler::add_user(
+        &state.db,
+        XPUser {
+            user_id: msg.author.id,
+            meta,
+        },
+    ) {
+        error!("Failed to save achievements! {:?}", why);
+        return;
+    }
+    let style = state.style(msg.guild_id);
+    let lang = state.lang(msg.guild_id, msg.author.id);
+    let text = i18n::fill(
+        lang,
+        "achievement.unlocked",
+        &[("user", format!("<@!{}>", msg.author.id.0)), ("badges", description)],
+    );
+    info!(
+        "{:?}",
+        msg.channel_id.send_message(|m| m.embed(|e| style
+            .embed(e, i18n::text(lang, "title.achievement"))
+            .description(text)
+            .timestamp(&msg.timestamp)))
+    );
+}
+
+/// the badges of a user's unlocked achievements, for `/stats`
+pub fn badge_row(achievements: &[Achievement], meta: &XPMeta) -> String {
+    achievements
+        .iter()
+        .filter(|a| meta.badges.contains(&a.id))
+        .map(|a| a.badge.clone())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
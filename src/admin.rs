@@ -0,0 +1,389 @@
+//! `/xp` and `/rank` commands for adjusting members' xp and the rank table by hand
+
+use super::{holds, i18n, ledger, ranks, reconcile, Handler, QueryError, Rank, State};
+use chrono::prelude::*;
+use log::{info, warn};
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::{
+    channel::Message,
+    id::{ChannelId, GuildId, RoleId, UserId},
+};
+use std::collections::HashSet;
+use std::thread;
+
+/// how long a bulk reset waits for `/xp confirm`
+const CONFIRM_WINDOW_SECS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResetTarget {
+    User(UserId),
+    Role(RoleId),
+    All,
+}
+
+impl ResetTarget {
+    fn parse(s: &str) -> Option<ResetTarget> {
+        if s == "all" {
+            Some(ResetTarget::All)
+        } else if let Ok(user) = s.parse::<UserId>() {
+            Some(ResetTarget::User(user))
+        } else if let Ok(role) = s.parse::<RoleId>() {
+            Some(ResetTarget::Role(role))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingReset {
+    /// only confirmed in the guild it was asked for in
+    guild: GuildId,
+    target: ResetTarget,
+    users: Vec<UserId>,
+    at: DateTime<Utc>,
+}
+
+pub fn xp(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let mut lock = ctx.data.lock();
+    let state = lock.get_mut::<State>().expect("Failed to get State");
+    let lang = state.lang(msg.guild_id, msg.author.id);
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => {
+            msg.reply(i18n::text(lang, "not_in_guild"))?;
+            return Ok(());
+        }
+    };
+    let sub = args.single::<String>().unwrap_or_default();
+    let reply = match &*sub {
+        "give" | "take" | "set" => adjust(state, guild, msg.author.id, &*sub, &mut args, lang),
+        "reset" => reset(state, guild, msg.author.id, &mut args, lang),
+        "confirm" => confirm(state, guild, msg.channel_id, msg.author.id, lang),
+        "freeze" | "unfreeze" => freeze(state, msg.author.id, &*sub, &mut args, lang),
+        _ => i18n::text(lang, "xp.usage").to_string(),
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+fn adjust(state: &State, guild: GuildId, admin: UserId, sub: &str, args: &mut Args, lang: i18n::Lang) -> String {
+    let user = match args.single::<UserId>() {
+        Ok(user) => user,
+        Err(_) => return i18n::text(lang, "xp.usage").to_string(),
+    };
+    let amount = match args.single::<f64>() {
+        Ok(amount) if amount.is_finite() && amount >= 0.0 => amount,
+        _ => return i18n::text(lang, "xp.amount").to_string(),
+    };
+    let reason = args.rest().trim();
+    let old = match Handler::find_user(&state.db, user) {
+        Ok(meta) => meta.map(|m| m.xp).unwrap_or(0.0),
+        Err(why) => return i18n::fill(lang, "error.update_user", &[("why", format!("{:?}", why))]),
+    };
+    let new = match sub {
+        "give" => old + amount,
+        "take" => (old - amount).max(0.0),
+        _ => amount,
+    };
+    let note = if reason.is_empty() {
+        format!("xp {} by <@!{}>", sub, admin.0)
+    } else {
+        format!("xp {} by <@!{}>: {}", sub, admin.0, reason)
+    };
+    match set_xp(state, guild, user, new, &*note) {
+        Ok(()) => i18n::fill(
+            lang,
+            "xp.adjusted",
+            &[
+                ("user", format!("<@!{}>", user.0)),
+                ("xp", i18n::number(lang, new)),
+                ("old", i18n::number(lang, old)),
+            ],
+        ),
+        Err(why) => i18n::fill(lang, "error.update_user", &[("why", format!("{:?}", why))]),
+    }
+}
+
+fn reset(state: &mut State, guild: GuildId, admin: UserId, args: &mut Args, lang: i18n::Lang) -> String {
+    let target = match args.single::<String>().ok().and_then(|s| ResetTarget::parse(&*s)) {
+        Some(target) => target,
+        None => return i18n::text(lang, "xp.usage").to_string(),
+    };
+    let users = match target {
+        ResetTarget::User(user) => {
+            let note = format!("xp reset by <@!{}>", admin.0);
+            return match set_xp(state, guild, user, 0.0, &*note) {
+                Ok(()) => i18n::fill(lang, "xp.reset_one", &[("user", format!("<@!{}>", user.0))]),
+                Err(why) => i18n::fill(lang, "error.reset_user", &[("why", format!("{:?}", why))]),
+            };
+        }
+        ResetTarget::Role(role) => match guild.to_guild_cached() {
+            Some(g) => g
+                .read()
+                .members
+                .values()
+                .filter(|m| m.roles.contains(&role))
+                .map(|m| m.user.read().id)
+                .collect::<Vec<UserId>>(),
+            None => return i18n::text(lang, "guild_not_cached").to_string(),
+        },
+        // the stored users who are members here, not those of other guilds
+        ResetTarget::All => {
+            let members = match guild.to_guild_cached() {
+                Some(g) => g.read().members.keys().cloned().collect::<HashSet<UserId>>(),
+                None => return i18n::text(lang, "guild_not_cached").to_string(),
+            };
+            match Handler::get_users(&state.db) {
+                Ok(users) => users
+                    .into_iter()
+                    .map(|u| u.user_id)
+                    .filter(|u| members.contains(u))
+                    .collect(),
+                Err(why) => return i18n::fill(lang, "error.grab_users", &[("why", format!("{:?}", why))]),
+            }
+        }
+    };
+    let count = users.len();
+    state.pending_resets.insert(
+        admin,
+        PendingReset {
+            guild,
+            target,
+            users,
+            at: Utc::now(),
+        },
+    );
+    i18n::fill(
+        lang,
+        "xp.reset_pending",
+        &[("count", count.to_string()), ("secs", CONFIRM_WINDOW_SECS.to_string())],
+    )
+}
+
+/// starts the confirmed reset in the background, each member's roles are
+/// synced over http so `all` can take minutes. the result goes to `channel`
+fn confirm(state: &mut State, guild: GuildId, channel: ChannelId, admin: UserId, lang: i18n::Lang) -> String {
+    // a reset asked for in another guild stays pending there
+    if state.pending_resets.get(&admin).map(|p| p.guild) != Some(guild) {
+        return i18n::text(lang, "xp.no_pending").to_string();
+    }
+    let pending = match state.pending_resets.remove(&admin) {
+        Some(pending) => pending,
+        None => return i18n::text(lang, "xp.no_pending").to_string(),
+    };
+    if Utc::now().signed_duration_since(pending.at) > chrono::Duration::seconds(CONFIRM_WINDOW_SECS) {
+        return i18n::text(lang, "xp.expired").to_string();
+    }
+    let note = format!("bulk xp reset ({:?}) by <@!{}>", pending.target, admin.0);
+    let (db, ranks, mode) = (state.db.clone(), state.ranks_for(Some(guild)).clone(), state.rank_mode(guild));
+    let count = pending.users.len();
+    thread::spawn(move || {
+        let failed = pending
+            .users
+            .iter()
+            .filter(|user| {
+                set_xp_with(&db, &ranks, mode, guild, **user, 0.0, &*note)
+                    .map_err(|why| warn!("Failed to reset {}: {:?}", user, why))
+                    .is_err()
+            })
+            .count();
+        info!("{}: {} reset, {} failed", note, count - failed, failed);
+        let done = i18n::fill(
+            lang,
+            "xp.reset_done",
+            &[("count", (count - failed).to_string()), ("failed", failed.to_string())],
+        );
+        if let Err(why) = channel.say(&*done) {
+            warn!("Failed to post the reset result: {:?}", why);
+        }
+    });
+    i18n::fill(lang, "xp.reset_started", &[("count", count.to_string())])
+}
+
+/// stores the new xp, notes the change in the ledger and syncs the member's rank roles
+fn set_xp(state: &State, guild: GuildId, user: UserId, xp: f64, note: &str) -> Result<(), QueryError> {
+    let ranks = state.ranks_for(Some(guild));
+    set_xp_with(&state.db, ranks, state.rank_mode(guild), guild, user, xp, note)
+}
+
+/// `set_xp` with what it needs from the state, for resets that run without it
+fn set_xp_with(
+    db: &redis::Client,
+    ranks: &[Rank],
+    mode: ranks::RankMode,
+    guild: GuildId,
+    user: UserId,
+    xp: f64,
+    note: &str,
+) -> Result<(), QueryError> {
+    let old = Handler::set_xp(db, user, xp)?;
+    ledger::record(db, user, xp - old, note)?;
+    let meta = Handler::get_user(db, user)?;
+    ranks::sync_user_ranks(guild, user, ranks, mode, &meta);
+    Ok(())
+}
+
+pub fn rank(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => {
+            msg.reply(i18n::text(i18n::Lang::default(), "not_in_guild"))?;
+            return Ok(());
+        }
+    };
+    let sub = args.single::<String>().unwrap_or_default();
+    if sub == "resync" {
+        // the job lives in `/ranks sync` now, this stays for muscle memory
+        msg.reply(&*reconcile::begin(ctx, guild, msg.channel_id, msg.author.id))?;
+        return Ok(());
+    }
+    let reply = {
+        let mut lock = ctx.data.lock();
+        let state = lock.get_mut::<State>().expect("Failed to get State");
+        let lang = state.lang(Some(guild), msg.author.id);
+        match &*sub {
+            "add" | "edit" => add_rank(state, guild, &mut args, lang),
+            "remove" => remove_rank(state, guild, &mut args, lang),
+            "list" => list_ranks(state, guild, lang),
+            "lock" | "unlock" => lock(state, guild, msg.author.id, &*sub, &mut args, lang),
+            _ => i18n::text(lang, "rank.usage").to_string(),
+        }
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+/// adds a rank, or moves/renames it if the role already is one
+fn add_rank(state: &mut State, guild: GuildId, args: &mut Args, lang: i18n::Lang) -> String {
+    let role = match args.single::<RoleId>() {
+        Ok(role) => role,
+        Err(_) => return i18n::text(lang, "rank.usage").to_string(),
+    };
+    let xp = match args.single::<f64>() {
+        Ok(xp) if xp.is_finite() && xp >= 0.0 => xp,
+        _ => return i18n::text(lang, "rank.threshold").to_string(),
+    };
+    let mention = format!("<@&{}>", role.0);
+    let name = args.rest().trim();
+    let name = if name.is_empty() {
+        None
+    } else {
+        Some(name.to_string())
+    };
+    let mut ranks = state.ranks_for(Some(guild)).clone();
+    let reply = match ranks.iter_mut().find(|r| r.role_id == role) {
+        Some(rank) => {
+            let old = rank.required_xp;
+            rank.required_xp = xp;
+            rank.name = name.or_else(|| rank.name.clone());
+            i18n::fill(
+                lang,
+                "rank.moved",
+                &[("role", mention), ("old", i18n::number(lang, old)), ("xp", i18n::number(lang, xp))],
+            )
+        }
+        None => {
+            ranks.push(Rank {
+                role_id: role,
+                required_xp: xp,
+                name,
+                requires: Default::default(),
+                rewards: Default::default(),
+            });
+            i18n::fill(lang, "rank.added", &[("role", mention), ("xp", i18n::number(lang, xp))])
+        }
+    };
+    match store_ranks(state, guild, ranks) {
+        Ok(()) => format!("{} {}", reply, i18n::text(lang, "rank.resync_hint")),
+        Err(why) => i18n::fill(lang, "error.save_ranks", &[("why", format!("{:?}", why))]),
+    }
+}
+
+fn remove_rank(state: &mut State, guild: GuildId, args: &mut Args, lang: i18n::Lang) -> String {
+    let role = match args.single::<RoleId>() {
+        Ok(role) => role,
+        Err(_) => return i18n::text(lang, "rank.usage").to_string(),
+    };
+    let mention = format!("<@&{}>", role.0);
+    let mut ranks = state.ranks_for(Some(guild)).clone();
+    let before = ranks.len();
+    ranks.retain(|r| r.role_id != role);
+    if ranks.len() == before {
+        return i18n::fill(lang, "rank.not_a_rank", &[("role", mention)]);
+    }
+    match store_ranks(state, guild, ranks) {
+        Ok(()) => format!(
+            "{} {}",
+            i18n::fill(lang, "rank.removed", &[("role", mention)]),
+            i18n::text(lang, "rank.resync_hint")
+        ),
+        Err(why) => i18n::fill(lang, "error.save_ranks", &[("why", format!("{:?}", why))]),
+    }
+}
+
+fn list_ranks(state: &State, guild: GuildId, lang: i18n::Lang) -> String {
+    let ranks = state.ranks_for(Some(guild));
+    if ranks.is_empty() {
+        return i18n::text(lang, "rank.none").to_string();
+    }
+    ranks
+        .iter()
+        .map(|r| {
+            let role = format!("<@&{}>", r.role_id.0);
+            let xp = i18n::number(lang, r.required_xp);
+            match r.name {
+                Some(ref name) => i18n::fill(lang, "rank.line_named", &[("role", role), ("name", name.clone()), ("xp", xp)]),
+                None => i18n::fill(lang, "rank.line", &[("role", role), ("xp", xp)]),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// saves the guild's ranks sorted by required xp and starts using them
+fn store_ranks(state: &mut State, guild: GuildId, mut ranks: Vec<Rank>) -> Result<(), QueryError> {
+    ranks::sort(&mut ranks);
+    ranks::save_guild_ranks(&state.db, guild, &ranks)?;
+    state.guild_ranks.insert(guild, ranks);
+    Ok(())
+}
+
+fn freeze(state: &State, admin: UserId, sub: &str, args: &mut Args, lang: i18n::Lang) -> String {
+    let user = match args.single::<UserId>() {
+        Ok(user) => user,
+        Err(_) => return i18n::text(lang, "xp.usage").to_string(),
+    };
+    let result = if sub == "unfreeze" {
+        holds::unfreeze(state, user, admin, lang)
+    } else {
+        match args.single::<String>().ok().and_then(|d| holds::parse_duration(&*d)) {
+            Some(duration) => holds::freeze(state, user, duration, admin, lang),
+            None => return i18n::text(lang, "hold.duration_hint").to_string(),
+        }
+    };
+    result.unwrap_or_else(|why| i18n::fill(lang, "error.update_user", &[("why", format!("{:?}", why))]))
+}
+
+fn lock(state: &State, guild: GuildId, admin: UserId, sub: &str, args: &mut Args, lang: i18n::Lang) -> String {
+    let user = match args.single::<UserId>() {
+        Ok(user) => user,
+        Err(_) => return i18n::text(lang, "rank.usage").to_string(),
+    };
+    let result = if sub == "unlock" {
+        holds::unlock(state, guild, user, admin, lang)
+    } else {
+        let role = match args.single::<RoleId>() {
+            Ok(role) => role,
+            Err(_) => return i18n::text(lang, "rank.usage").to_string(),
+        };
+        match args.single::<String>().ok().and_then(|d| holds::parse_duration(&*d)) {
+            Some(duration) => holds::lock(state, guild, user, role, duration, admin, lang),
+            None => return i18n::text(lang, "hold.duration_hint").to_string(),
+        }
+    };
+    result.unwrap_or_else(|why| i18n::fill(lang, "error.update_user", &[("why", format!("{:?}", why))]))
+}
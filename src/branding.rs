@@ -0,0 +1,81 @@
+//! how the embeds look in a guild: the name and icon in the author line, the
+//! colour and the footer. `[branding]` sets them for every guild and a
+//! guild's own `branding` table overrides single fields
+
+use super::config;
+use serenity::builder::CreateEmbed;
+use serenity::model::id::GuildId;
+use serenity::utils::Colour;
+
+/// used when neither the config nor the cache has a name
+const FALLBACK_NAME: &str = "Levels";
+
+#[derive(Debug, Clone)]
+pub struct Style {
+    pub name: String,
+    pub icon_url: Option<String>,
+    pub colour: Option<Colour>,
+    pub footer: Option<String>,
+}
+
+/// parses `#rrggbb` or `rrggbb`
+pub fn parse_colour(raw: &str) -> Option<Colour> {
+    let hex = raw.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(Colour::new)
+}
+
+impl Style {
+    /// the guild's overrides on top of the global branding. the name falls
+    /// back to the bot's own, the icon to the guild's
+    pub fn resolve(global: &config::Branding, own: Option<&config::Branding>, guild: Option<GuildId>) -> Style {
+        let pick = |field: fn(&config::Branding) -> &Option<String>| {
+            own.and_then(|b| field(b).clone()).or_else(|| field(global).clone())
+        };
+        Style {
+            name: pick(|b| &b.name).unwrap_or_else(|| {
+                let name = serenity::CACHE.read().user.name.clone();
+                if name.is_empty() {
+                    FALLBACK_NAME.to_string()
+                } else {
+                    name
+                }
+            }),
+            icon_url: pick(|b| &b.icon_url)
+                .or_else(|| guild.and_then(|g| g.to_guild_cached()).and_then(|g| g.read().icon_url())),
+            colour: pick(|b| &b.colour).and_then(|c| parse_colour(&*c)),
+            footer: pick(|b| &b.footer),
+        }
+    }
+
+    /// embed author line, e.g. "Blast — Leaderboard"
+    pub fn title(&self, what: &str) -> String {
+        format!("{} — {}", self.name, what)
+    }
+
+    /// sets the author, colour and footer of an embed
+    pub fn embed(&self, e: CreateEmbed, what: &str) -> CreateEmbed {
+        let title = self.title(what);
+        let mut e = e.author(|a| match self.icon_url {
+            Some(ref icon) => a.name(&*title).icon_url(&**icon),
+            None => a.name(&*title),
+        });
+        if let Some(colour) = self.colour {
+            e = e.colour(colour);
+        }
+        if let Some(ref footer) = self.footer {
+            e = e.footer(|f| f.text(&**footer));
+        }
+        e
+    }
+
+    /// a footer of the embed's own followed by the branding's
+    pub fn footer_with(&self, text: &str) -> String {
+        match self.footer {
+            Some(ref footer) => format!("{} · {}", text, footer),
+            None => text.to_string(),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! daily & weekly xp caps, enforced by `Handler::add_xp` and on the rank
+//! tracks
+
+use super::{holds, prestige, Settings, XPMeta};
+use chrono::prelude::*;
+
+/// xp already awarded today and this week. a window that has rolled over
+/// since `last_activity` counts as empty
+pub fn window_totals(meta: &XPMeta, now: DateTime<Utc>) -> (f64, f64) {
+    let last = meta.last_activity;
+    let daily = if last.date() == now.date() {
+        meta.daily_xp
+    } else {
+        0.0
+    };
+    let weekly = if last.iso_week() == now.iso_week() {
+        meta.weekly_xp
+    } else {
+        0.0
+    };
+    (daily, weekly)
+}
+
+/// remaining (daily, weekly) allowance, `None` where no cap is configured
+pub fn remaining(meta: &XPMeta, settings: &Settings, now: DateTime<Utc>) -> (Option<f64>, Option<f64>) {
+    let (daily, weekly) = window_totals(meta, now);
+    (
+        settings.daily_cap.map(|cap| (cap - daily).max(0.0)),
+        settings.weekly_cap.map(|cap| (cap - weekly).max(0.0)),
+    )
+}
+
+/// the part of `xp` that fits in both windows
+pub fn clamp(xp: f64, meta: &XPMeta, settings: &Settings, now: DateTime<Utc>) -> f64 {
+    let (daily, weekly) = remaining(meta, settings, now);
+    [daily, weekly]
+        .iter()
+        .filter_map(|r| *r)
+        .fold(xp, f64::min)
+        .max(0.0)
+}
+
+/// what an award of `xp` is worth to the member: scaled by their prestige,
+/// then truncated by the caps. nothing while they are frozen
+pub fn grantable(xp: f64, meta: &XPMeta, settings: &Settings, now: DateTime<Utc>) -> f64 {
+    if holds::frozen(meta, now) {
+        0.0
+    } else {
+        clamp(xp * prestige::multiplier(meta, settings), meta, settings, now)
+    }
+}
@@ -0,0 +1,417 @@
+//! rank cards: `/stats` drawn as a png with the avatar, name, rank, a
+//! progress bar, position and xp, and `/leaderboard image` as a table of the
+//! top members. rendered here with `image` and `rusttype`, the look comes
+//! from `[cards]` and a guild's own cards table
+
+use super::{branding, config, i18n, ranks, Rank, Rendering};
+use image::{DynamicImage, FilterType, ImageOutputFormat, Rgba, RgbaImage};
+use log::warn;
+use rusttype::{point, Font, Scale};
+use serenity::model::{id::GuildId, user::User};
+use serenity::prelude::Mutex;
+use serenity::utils::Colour;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::{fs, io};
+
+const WIDTH: u32 = 934;
+const HEIGHT: u32 = 282;
+const AVATAR_SIZE: u32 = 180;
+/// `/leaderboard image` rows, the podium and the table below it
+pub const MAX_ROWS: usize = 10;
+const ROW_HEIGHT: u32 = 96;
+const ROW_AVATAR_SIZE: u32 = 72;
+/// avatars kept before the cache starts over
+const CACHED_AVATARS: usize = 500;
+
+#[derive(Debug)]
+pub enum CardError {
+    Io(io::Error),
+    Font(rusttype::Error),
+    Image(image::ImageError),
+}
+
+impl From<io::Error> for CardError {
+    fn from(e: io::Error) -> CardError {
+        CardError::Io(e)
+    }
+}
+
+impl From<rusttype::Error> for CardError {
+    fn from(e: rusttype::Error) -> CardError {
+        CardError::Font(e)
+    }
+}
+
+impl From<image::ImageError> for CardError {
+    fn from(e: image::ImageError) -> CardError {
+        CardError::Image(e)
+    }
+}
+
+/// the resolved look of a guild's cards
+#[derive(Debug, Clone)]
+pub struct Look {
+    pub font: String,
+    /// cards without `/stats card`
+    pub default: bool,
+    pub background: Rgba<u8>,
+    pub background_image: Option<String>,
+    pub accent: Rgba<u8>,
+    pub text: Rgba<u8>,
+}
+
+fn rgba(colour: Colour) -> Rgba<u8> {
+    Rgba {
+        data: [colour.r(), colour.g(), colour.b(), 255],
+    }
+}
+
+impl Look {
+    /// the guild's overrides on top of `[cards]`, none without a font
+    pub fn resolve(global: Option<&config::Cards>, own: Option<&config::Cards>) -> Option<Look> {
+        let pick = |field: fn(&config::Cards) -> Option<String>| own.and_then(field).or_else(|| global.and_then(field));
+        let colour = |field: fn(&config::Cards) -> Option<String>, fallback: u32| {
+            rgba(pick(field).and_then(|c| branding::parse_colour(&*c)).unwrap_or_else(|| Colour::new(fallback)))
+        };
+        Some(Look {
+            font: pick(|c| c.font.clone())?,
+            default: own
+                .and_then(|c| c.default)
+                .or_else(|| global.and_then(|c| c.default))
+                .unwrap_or(false),
+            background: colour(|c| c.background.clone(), 0x23_27_2a),
+            background_image: pick(|c| c.background_image.clone()),
+            accent: colour(|c| c.accent.clone(), 0x58_65_f2),
+            text: colour(|c| c.text.clone(), 0xff_ff_ff),
+        })
+    }
+}
+
+/// a png version of the avatar, the webp one discord hands out by default
+/// can't be decoded everywhere
+pub fn avatar_url(user: &User) -> String {
+    match user.avatar {
+        Some(ref hash) => format!("https://cdn.discordapp.com/avatars/{}/{}.png?size=256", user.id.0, hash),
+        None => user.default_avatar_url(),
+    }
+}
+
+/// decoded avatars by url, a changed avatar gets a new url
+#[derive(Debug, Clone, Default)]
+pub struct Avatars(Arc<Mutex<HashMap<String, RgbaImage>>>);
+
+impl Avatars {
+    /// the cached avatar of `user`, fetched if it isn't cached yet
+    fn get(&self, user: &User) -> Option<RgbaImage> {
+        let url = avatar_url(user);
+        if let Some(avatar) = self.0.lock().get(&url) {
+            return Some(avatar.clone());
+        }
+        // not holding the lock while downloading
+        let avatar = fetch_avatar(user, &url)?.to_rgba();
+        let mut cache = self.0.lock();
+        if cache.len() >= CACHED_AVATARS {
+            cache.clear();
+        }
+        cache.insert(url, avatar.clone());
+        Some(avatar)
+    }
+}
+
+fn fetch_avatar(user: &User, url: &str) -> Option<DynamicImage> {
+    let mut body = Vec::new();
+    let fetched = reqwest::get(url)
+        .and_then(|res| res.error_for_status())
+        .and_then(|mut res| res.copy_to(&mut body));
+    if let Err(why) = fetched {
+        warn!("Could not fetch the avatar of {}: {:?}", user.id, why);
+        return None;
+    }
+    image::load_from_memory(&body)
+        .map_err(|why| warn!("Could not read the avatar of {}: {:?}", user.id, why))
+        .ok()
+}
+
+/// draws `colour` over the pixel with the given coverage, ignoring pixels
+/// outside the image
+fn blend(img: &mut RgbaImage, x: i32, y: i32, colour: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x as u32 >= img.width() || y as u32 >= img.height() {
+        return;
+    }
+    let alpha = coverage * f32::from(colour.data[3]) / 255.0;
+    let pixel = img.get_pixel_mut(x as u32, y as u32);
+    for i in 0..3 {
+        let (under, over) = (f32::from(pixel.data[i]), f32::from(colour.data[i]));
+        pixel.data[i] = (under + (over - under) * alpha).round() as u8;
+    }
+    pixel.data[3] = 255;
+}
+
+fn fill_rect(img: &mut RgbaImage, x: i32, y: i32, w: i32, h: i32, colour: Rgba<u8>) {
+    for py in y..y + h {
+        for px in x..x + w {
+            blend(img, px, py, colour, 1.0);
+        }
+    }
+}
+
+/// a rectangle with round ends, `h` high
+fn fill_pill(img: &mut RgbaImage, x: i32, y: i32, w: i32, h: i32, colour: Rgba<u8>) {
+    let r = h as f32 / 2.0;
+    for py in y..y + h {
+        for px in x..x + w {
+            let cy = py as f32 + 0.5 - (y as f32 + r);
+            let cx = (px as f32 + 0.5).max(x as f32 + r).min((x + w) as f32 - r);
+            let d = ((px as f32 + 0.5 - cx).powi(2) + cy.powi(2)).sqrt();
+            blend(img, px, py, colour, (r - d + 0.5).max(0.0).min(1.0));
+        }
+    }
+}
+
+fn text_width(font: &Font, size: f32, text: &str) -> f32 {
+    font.layout(text, Scale::uniform(size), point(0.0, 0.0))
+        .last()
+        .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width)
+        .unwrap_or(0.0)
+}
+
+/// draws `text` with its baseline at `y`
+fn draw_text(img: &mut RgbaImage, font: &Font, size: f32, x: f32, y: f32, colour: Rgba<u8>, text: &str) {
+    for glyph in font.layout(text, Scale::uniform(size), point(x, y)) {
+        if let Some(bb) = glyph.pixel_bounding_box() {
+            glyph.draw(|gx, gy, coverage| blend(img, bb.min.x + gx as i32, bb.min.y + gy as i32, colour, coverage));
+        }
+    }
+}
+
+/// `text` cut down with an ellipsis until it is at most `width` wide
+fn fit(font: &Font, size: f32, text: &str, width: f32) -> String {
+    if text_width(font, size, text) <= width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let cut = format!("{}…", chars.iter().collect::<String>());
+        if text_width(font, size, &cut) <= width {
+            return cut;
+        }
+    }
+    String::new()
+}
+
+/// the avatar cropped to a circle at `(x, y)`
+fn draw_avatar(img: &mut RgbaImage, avatar: RgbaImage, x: i32, y: i32, size: u32) {
+    let avatar = DynamicImage::ImageRgba8(avatar)
+        .resize_to_fill(size, size, FilterType::Triangle)
+        .to_rgba();
+    let r = size as f32 / 2.0;
+    for (ax, ay, pixel) in avatar.enumerate_pixels() {
+        let d = ((ax as f32 + 0.5 - r).powi(2) + (ay as f32 + 0.5 - r).powi(2)).sqrt();
+        let coverage = (r - d + 0.5).max(0.0).min(1.0);
+        blend(img, x + ax as i32, y + ay as i32, *pixel, coverage);
+    }
+}
+
+/// the colour of a rank's role, none if it has the default colour
+fn role_colour(guild: Option<GuildId>, rank: &Rank) -> Option<Colour> {
+    let guild = guild?.to_guild_cached()?;
+    let colour = guild.read().roles.get(&rank.role_id).map(|role| role.colour);
+    colour.filter(|colour| colour.0 != 0)
+}
+
+/// the rank's own name, else its role's
+fn rank_name(guild: Option<GuildId>, rank: &Rank) -> String {
+    rank.name.clone().unwrap_or_else(|| {
+        guild
+            .and_then(|g| g.to_guild_cached())
+            .and_then(|g| g.read().roles.get(&rank.role_id).map(|r| r.name.clone()))
+            .unwrap_or_else(|| rank.role_id.0.to_string())
+    })
+}
+
+/// the background and its image, with a darker panel inset by `margin` that
+/// keeps the text readable on busy backgrounds
+fn canvas(look: &Look, width: u32, height: u32, margin: i32) -> Result<RgbaImage, CardError> {
+    let mut img = RgbaImage::from_pixel(width, height, look.background);
+    if let Some(ref path) = look.background_image {
+        let background = image::open(path)?.resize_to_fill(width, height, FilterType::Triangle).to_rgba();
+        for (x, y, pixel) in background.enumerate_pixels() {
+            blend(&mut img, x as i32, y as i32, *pixel, 1.0);
+        }
+    }
+    let (w, h) = (width as i32 - 2 * margin, height as i32 - 2 * margin);
+    fill_rect(&mut img, margin, margin, w, h, Rgba { data: [0, 0, 0, 90] });
+    Ok(img)
+}
+
+/// the avatar of `user`, or a circle in the accent colour without one
+fn draw_avatar_of(img: &mut RgbaImage, avatars: &Avatars, look: &Look, user: &User, x: i32, y: i32, size: u32) {
+    match avatars.get(user) {
+        Some(avatar) => draw_avatar(img, avatar, x, y, size),
+        None => fill_pill(img, x, y, size as i32, size as i32, look.accent),
+    }
+}
+
+/// how far along `xp` is toward the next rank, and the xp label for it
+fn progress(ranks: &[Rank], xp: f64, lang: i18n::Lang) -> (f64, String) {
+    let current = ranks::rank_for(ranks, xp);
+    match ranks::next_rank(ranks, xp) {
+        Some(next) => {
+            let from = current.map(|r| r.required_xp).unwrap_or(0.0);
+            let span = (next.required_xp - from).max(std::f64::EPSILON);
+            let label = i18n::fill(
+                lang,
+                "card.xp",
+                &[("xp", i18n::number(lang, xp)), ("goal", i18n::number(lang, next.required_xp))],
+            );
+            (((xp - from) / span).max(0.0).min(1.0), label)
+        }
+        None => (1.0, i18n::fill(lang, "card.xp_max", &[("xp", i18n::number(lang, xp))])),
+    }
+}
+
+fn draw_bar(img: &mut RgbaImage, x: i32, y: i32, w: i32, h: i32, progress: f64, colour: Rgba<u8>) {
+    fill_pill(img, x, y, w, h, Rgba { data: [0, 0, 0, 140] });
+    let filled = (f64::from(w) * progress) as i32;
+    if filled > 0 {
+        fill_pill(img, x, y, filled.max(h), h, colour);
+    }
+}
+
+/// the colour of the rank `xp` is at, else the accent
+fn rank_colour(look: &Look, ranks: &[Rank], xp: f64, guild: Option<GuildId>) -> Rgba<u8> {
+    ranks::rank_for(ranks, xp)
+        .and_then(|r| role_colour(guild, r))
+        .map(rgba)
+        .unwrap_or(look.accent)
+}
+
+fn muted(look: &Look) -> Rgba<u8> {
+    Rgba {
+        data: [look.text.data[0], look.text.data[1], look.text.data[2], 170],
+    }
+}
+
+fn png(img: RgbaImage) -> Result<Vec<u8>, CardError> {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(img).write_to(&mut png, ImageOutputFormat::PNG)?;
+    Ok(png)
+}
+
+/// renders the rank card of `user` as a png
+pub fn render(
+    look: &Look,
+    avatars: &Avatars,
+    user: &User,
+    xp: f64,
+    ranks: &[Rank],
+    guild: Option<GuildId>,
+    rendering: &Rendering,
+) -> Result<Vec<u8>, CardError> {
+    let lang = rendering.lang;
+    let font = Font::from_bytes(fs::read(&look.font)?)?;
+    let mut img = canvas(look, WIDTH, HEIGHT, 20)?;
+
+    let avatar_at = (50, (HEIGHT - AVATAR_SIZE) as i32 / 2);
+    draw_avatar_of(&mut img, avatars, look, user, avatar_at.0, avatar_at.1, AVATAR_SIZE);
+
+    let current = ranks::rank_for(ranks, xp);
+    let colour = rank_colour(look, ranks, xp, guild);
+    let muted = muted(look);
+    let left = 270.0;
+    let right = WIDTH as f32 - 50.0;
+
+    // top right: position and level
+    let position = rendering.position.map(|p| format!("#{}", p)).unwrap_or_default();
+    let level = i18n::fill(lang, "card.level", &[("level", ranks::level(ranks, xp).to_string())]);
+    let position_x = right - text_width(&font, 56.0, &position);
+    draw_text(&mut img, &font, 56.0, position_x, 95.0, look.text, &position);
+    let level_x = position_x - 20.0 - text_width(&font, 30.0, &level);
+    draw_text(&mut img, &font, 30.0, level_x, 95.0, muted, &level);
+
+    // the name and the rank below it
+    let name = fit(&font, 44.0, &user.name, level_x - left - 20.0);
+    draw_text(&mut img, &font, 44.0, left, 95.0, look.text, &name);
+    let rank = match current {
+        Some(rank) => rank_name(guild, rank),
+        None => i18n::text(lang, "card.unranked").to_string(),
+    };
+    draw_text(&mut img, &font, 32.0, left, 145.0, colour, &fit(&font, 32.0, &rank, right - left));
+
+    // xp and the progress bar toward the next rank
+    let (progress, label) = progress(ranks, xp, lang);
+    draw_text(&mut img, &font, 26.0, right - text_width(&font, 26.0, &label), 190.0, muted, &label);
+    draw_bar(&mut img, left as i32, 205, (right - left) as i32, 34, progress, colour);
+
+    png(img)
+}
+
+/// the medal colour of the podium places
+fn medal(position: usize) -> Option<Rgba<u8>> {
+    match position {
+        1 => Some(Rgba { data: [0xf1, 0xc4, 0x0f, 255] }),
+        2 => Some(Rgba { data: [0xbd, 0xc3, 0xc7, 255] }),
+        3 => Some(Rgba { data: [0xcd, 0x7f, 0x32, 255] }),
+        _ => None,
+    }
+}
+
+/// renders the leaderboard as a png, a row per member in `top` with their
+/// position and xp. the podium places get their medal's colour
+pub fn render_leaderboard(
+    look: &Look,
+    avatars: &Avatars,
+    top: &[(usize, User, f64)],
+    ranks: &[Rank],
+    guild: Option<GuildId>,
+    lang: i18n::Lang,
+) -> Result<Vec<u8>, CardError> {
+    let font = Font::from_bytes(fs::read(&look.font)?)?;
+    let rows = top.len().min(MAX_ROWS).max(1) as u32;
+    let height = rows * ROW_HEIGHT + 40;
+    let mut img = canvas(look, WIDTH, height, 10)?;
+    let muted = muted(look);
+    let right = WIDTH as f32 - 40.0;
+
+    for (i, &(position, ref user, xp)) in top.iter().take(MAX_ROWS).enumerate() {
+        let top_y = 20 + i as i32 * ROW_HEIGHT as i32;
+        let colour = rank_colour(look, ranks, xp, guild);
+        if i % 2 == 1 {
+            fill_rect(&mut img, 10, top_y, WIDTH as i32 - 20, ROW_HEIGHT as i32, Rgba { data: [0, 0, 0, 40] });
+        }
+
+        // the position, in a medal for the podium
+        let place = position.to_string();
+        let place_size = 34.0;
+        let place_x = 75.0 - text_width(&font, place_size, &place) / 2.0;
+        let baseline = top_y as f32 + ROW_HEIGHT as f32 / 2.0 + place_size / 3.0;
+        if let Some(medal) = medal(position) {
+            let d = 56;
+            fill_pill(&mut img, 75 - d / 2, top_y + (ROW_HEIGHT as i32 - d) / 2, d, d, medal);
+            draw_text(&mut img, &font, place_size, place_x, baseline, look.background, &place);
+        } else {
+            draw_text(&mut img, &font, place_size, place_x, baseline, muted, &place);
+        }
+
+        let avatar_y = top_y + (ROW_HEIGHT - ROW_AVATAR_SIZE) as i32 / 2;
+        draw_avatar_of(&mut img, avatars, look, user, 125, avatar_y, ROW_AVATAR_SIZE);
+
+        // name and level on top, the xp bar with its label below
+        let left = 125.0 + ROW_AVATAR_SIZE as f32 + 25.0;
+        let level = i18n::fill(lang, "card.level", &[("level", ranks::level(ranks, xp).to_string())]);
+        let level_x = right - text_width(&font, 26.0, &level);
+        draw_text(&mut img, &font, 26.0, level_x, top_y as f32 + 42.0, colour, &level);
+        let name = fit(&font, 32.0, &user.name, level_x - left - 20.0);
+        draw_text(&mut img, &font, 32.0, left, top_y as f32 + 42.0, look.text, &name);
+
+        let (progress, label) = progress(ranks, xp, lang);
+        let label_x = right - text_width(&font, 20.0, &label);
+        draw_text(&mut img, &font, 20.0, label_x, top_y as f32 + 76.0, muted, &label);
+        let bar_w = (label_x - 20.0 - left) as i32;
+        draw_bar(&mut img, left as i32, top_y + 58, bar_w, 20, progress, colour);
+    }
+
+    png(img)
+}
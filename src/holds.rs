@@ -0,0 +1,209 @@
+//! moderation holds: `/xp freeze` stops a member from gaining xp and
+//! `/rank lock` pins them at a rank, both until they expire. they live in the
+//! user record so the award path and every role sync see them, and `tick`
+//! lifts them once they run out
+
+use super::{i18n, ledger, ranks, Handler, QueryError, Rank, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::{error, info};
+use serenity::model::id::{GuildId, RoleId, UserId};
+
+/// a member pinned at a rank in one guild
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankLock {
+    pub role: RoleId,
+    pub until: DateTime<Utc>,
+    pub by: UserId,
+}
+
+/// the longest hold, in minutes: a year
+const MAX_HOLD_MINUTES: i64 = 366 * 24 * 60;
+
+/// parses durations like `30m`, `12h`, `7d` or `2w`, up to a year
+pub fn parse_duration(arg: &str) -> Option<chrono::Duration> {
+    // the unit is the last character, which needn't be a single byte
+    let (at, _) = arg.char_indices().last()?;
+    let (amount, unit) = arg.split_at(at);
+    let amount = amount.parse::<i64>().ok().filter(|a| *a > 0)?;
+    let minutes = match unit {
+        "m" => 1,
+        "h" => 60,
+        "d" => 24 * 60,
+        "w" => 7 * 24 * 60,
+        _ => return None,
+    };
+    // chrono panics on durations out of its range, so cap before building one
+    amount
+        .checked_mul(minutes)
+        .filter(|m| *m <= MAX_HOLD_MINUTES)
+        .map(chrono::Duration::minutes)
+}
+
+pub fn frozen(meta: &XPMeta, now: DateTime<Utc>) -> bool {
+    meta.frozen_until.map(|until| now < until).unwrap_or(false)
+}
+
+/// the rank the member is locked at in `guild`, if it is one of `ranks`
+pub fn locked_rank<'a>(ranks: &'a [Rank], guild: GuildId, meta: &XPMeta, now: DateTime<Utc>) -> Option<&'a Rank> {
+    meta.locks
+        .get(&guild.0)
+        .filter(|lock| now < lock.until)
+        .and_then(|lock| ranks.iter().find(|r| r.role_id == lock.role))
+}
+
+fn save(state: &State, user: UserId, meta: XPMeta) -> Result<(), QueryError> {
+    Handler::add_user(&state.db, XPUser { user_id: user, meta })?;
+    Ok(())
+}
+
+/// the replies of these are written in `lang`
+pub fn freeze(
+    state: &State,
+    user: UserId,
+    duration: chrono::Duration,
+    by: UserId,
+    lang: i18n::Lang,
+) -> Result<String, QueryError> {
+    let until = match Utc::now().checked_add_signed(duration) {
+        Some(until) => until,
+        None => return Ok(i18n::text(lang, "hold.duration_hint").to_string()),
+    };
+    let mut meta = Handler::find_user(&state.db, user)?.unwrap_or_else(|| XPMeta::new(Utc::now()));
+    meta.frozen_until = Some(until);
+    save(state, user, meta)?;
+    ledger::record(&state.db, user, 0.0, &*format!("xp frozen until {} by <@!{}>", until.format("%Y-%m-%d %H:%M"), by.0))?;
+    Ok(i18n::fill(
+        lang,
+        "hold.frozen",
+        &[("user", format!("<@!{}>", user.0)), ("until", until.format("%Y-%m-%d %H:%M").to_string())],
+    ))
+}
+
+pub fn unfreeze(state: &State, user: UserId, by: UserId, lang: i18n::Lang) -> Result<String, QueryError> {
+    let mention = format!("<@!{}>", user.0);
+    let mut meta = match Handler::get_user(&state.db, user) {
+        Ok(ref meta) if frozen(meta, Utc::now()) => meta.clone(),
+        _ => return Ok(i18n::fill(lang, "hold.not_frozen", &[("user", mention)])),
+    };
+    meta.frozen_until = None;
+    save(state, user, meta)?;
+    ledger::record(&state.db, user, 0.0, &*format!("xp freeze lifted by <@!{}>", by.0))?;
+    Ok(i18n::fill(lang, "hold.unfrozen", &[("user", mention)]))
+}
+
+pub fn lock(
+    state: &State,
+    guild: GuildId,
+    user: UserId,
+    role: RoleId,
+    duration: chrono::Duration,
+    by: UserId,
+    lang: i18n::Lang,
+) -> Result<String, QueryError> {
+    let ranks = state.ranks_for(Some(guild));
+    if !ranks.iter().any(|r| r.role_id == role) {
+        return Ok(i18n::fill(lang, "rank.not_a_rank", &[("role", format!("<@&{}>", role.0))]));
+    }
+    let until = match Utc::now().checked_add_signed(duration) {
+        Some(until) => until,
+        None => return Ok(i18n::text(lang, "hold.duration_hint").to_string()),
+    };
+    let mut meta = Handler::find_user(&state.db, user)?.unwrap_or_else(|| XPMeta::new(Utc::now()));
+    meta.locks.insert(guild.0, RankLock { role, until, by });
+    save(state, user, meta.clone())?;
+    ledger::record(
+        &state.db,
+        user,
+        0.0,
+        &*format!("locked at <@&{}> until {} by <@!{}>", role.0, until.format("%Y-%m-%d %H:%M"), by.0),
+    )?;
+    ranks::sync_user_ranks(guild, user, ranks, state.rank_mode(guild), &meta);
+    Ok(i18n::fill(
+        lang,
+        "hold.locked",
+        &[
+            ("user", format!("<@!{}>", user.0)),
+            ("role", format!("<@&{}>", role.0)),
+            ("until", until.format("%Y-%m-%d %H:%M").to_string()),
+        ],
+    ))
+}
+
+pub fn unlock(state: &State, guild: GuildId, user: UserId, by: UserId, lang: i18n::Lang) -> Result<String, QueryError> {
+    let mention = format!("<@!{}>", user.0);
+    let mut meta = match Handler::get_user(&state.db, user) {
+        Ok(ref meta) if meta.locks.contains_key(&guild.0) => meta.clone(),
+        _ => return Ok(i18n::fill(lang, "hold.not_locked", &[("user", mention)])),
+    };
+    meta.locks.remove(&guild.0);
+    save(state, user, meta.clone())?;
+    ledger::record(&state.db, user, 0.0, &*format!("rank lock lifted by <@!{}>", by.0))?;
+    ranks::sync_user_ranks(guild, user, state.ranks_for(Some(guild)), state.rank_mode(guild), &meta);
+    Ok(i18n::fill(lang, "hold.unlocked", &[("user", mention)]))
+}
+
+/// lifts the holds that ran out, run from the periodic job
+pub fn tick(state: &State) {
+    let users = match Handler::get_users(&state.db) {
+        Ok(users) => users,
+        Err(why) => {
+            error!("Failed to check holds: {:?}", why);
+            return;
+        }
+    };
+    let now = Utc::now();
+    for user in users {
+        let mut meta = user.meta;
+        let thawed = meta.frozen_until.map(|until| now >= until).unwrap_or(false);
+        let expired: Vec<u64> = meta
+            .locks
+            .iter()
+            .filter(|(_, lock)| now >= lock.until)
+            .map(|(guild, _)| *guild)
+            .collect();
+        if !thawed && expired.is_empty() {
+            continue;
+        }
+        if thawed {
+            meta.frozen_until = None;
+        }
+        for guild in &expired {
+            meta.locks.remove(guild);
+        }
+        if let Err(why) = save(state, user.user_id, meta.clone()) {
+            error!("Failed to lift holds of {}: {:?}", user.user_id, why);
+            continue;
+        }
+        if thawed {
+            info!("XP freeze of {} expired", user.user_id);
+            let _ = ledger::record(&state.db, user.user_id, 0.0, "xp freeze expired");
+        }
+        for &guild in &expired {
+            let guild = GuildId::from(guild);
+            info!("Rank lock of {} in guild {} expired", user.user_id, guild);
+            let _ = ledger::record(&state.db, user.user_id, 0.0, "rank lock expired");
+            ranks::sync_user_ranks(guild, user.user_id, state.ranks_for(Some(guild)), state.rank_mode(guild), &meta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_parse_without_panicking() {
+        assert_eq!(parse_duration("12h"), Some(chrono::Duration::hours(12)));
+        assert_eq!(parse_duration("2w"), Some(chrono::Duration::weeks(2)));
+        assert_eq!(parse_duration("7д"), None);
+        assert_eq!(parse_duration("д"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("0d"), None);
+        assert_eq!(parse_duration("52w"), Some(chrono::Duration::weeks(52)));
+        assert_eq!(parse_duration("367d"), None);
+        assert_eq!(parse_duration("99999999d"), None);
+        assert_eq!(parse_duration("99999999999999w"), None);
+        assert_eq!(parse_duration("9223372036854775807m"), None);
+    }
+}
@@ -0,0 +1,194 @@
+//! the language members read the bot in. a guild picks one with `language`
+//! in its `[[guilds]]` entry and members can choose their own with
+//! `/language`. texts come from the catalogs in `locales`, numbers are
+//! written the way the language writes them
+
+use super::{locales, Handler, QueryError, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::warn;
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::{channel::Message, id::UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    De,
+    Es,
+}
+
+pub const ALL: &[Lang] = &[Lang::En, Lang::De, Lang::Es];
+
+impl Default for Lang {
+    fn default() -> Lang {
+        Lang::En
+    }
+}
+
+impl Lang {
+    /// parses a code like `de` or `es-ES`
+    pub fn parse(code: &str) -> Option<Lang> {
+        let code = code.to_lowercase();
+        match code.split(|c| c == '-' || c == '_').next() {
+            Some("en") => Some(Lang::En),
+            Some("de") => Some(Lang::De),
+            Some("es") => Some(Lang::Es),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+            Lang::Es => "es",
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => locales::EN,
+            Lang::De => locales::DE,
+            Lang::Es => locales::ES,
+        }
+    }
+
+    /// group separator, decimal separator and the digits before grouping starts
+    fn separators(self) -> (char, char, usize) {
+        match self {
+            Lang::En => (',', '.', 4),
+            Lang::De => ('.', ',', 4),
+            // spanish leaves four digit numbers ungrouped
+            Lang::Es => ('.', ',', 5),
+        }
+    }
+}
+
+/// the text for `key`, the english one if the language lacks it
+pub fn text(lang: Lang, key: &str) -> &'static str {
+    let find = |catalog: &'static [(&'static str, &'static str)]| catalog.iter().find(|(k, _)| *k == key).map(|(_, t)| *t);
+    find(lang.catalog()).or_else(|| find(locales::EN)).unwrap_or_else(|| {
+        warn!("No text for {:?}", key);
+        ""
+    })
+}
+
+/// `text` with its `{placeholders}` filled in
+pub fn fill(lang: Lang, key: &str, vars: &[(&str, String)]) -> String {
+    let mut out = text(lang, key).to_string();
+    for (name, value) in vars {
+        out = out.replace(&*format!("{{{}}}", name), value);
+    }
+    out
+}
+
+/// `x` with up to two decimals, e.g. 1,234.5 in english and 1.234,5 in german
+pub fn number(lang: Lang, x: f64) -> String {
+    let (group, decimal, min_digits) = lang.separators();
+    let fixed = format!("{:.2}", x.abs());
+    let (int, frac) = fixed.split_at(fixed.find('.').unwrap_or(fixed.len()));
+    let frac = frac.trim_start_matches('.').trim_end_matches('0');
+    let mut out = String::new();
+    if x < 0.0 && fixed != "0.00" {
+        out.push('-');
+    }
+    for (i, digit) in int.chars().enumerate() {
+        if int.len() >= min_digits && i > 0 && (int.len() - i) % 3 == 0 {
+            out.push(group);
+        }
+        out.push(digit);
+    }
+    if !frac.is_empty() {
+        out.push(decimal);
+        out.push_str(frac);
+    }
+    out
+}
+
+/// the language codes with their names, for replies
+fn available() -> String {
+    ALL.iter()
+        .map(|l| format!("`{}` ({})", l.code(), text(*l, "language.name")))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// `/language`, `/language <code>` and `/language reset`
+pub fn command(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let arg = args.single::<String>().unwrap_or_default();
+    let reply = {
+        let lock = ctx.data.lock();
+        let state = lock.get::<State>().expect("Failed to get State");
+        let current = state.lang(msg.guild_id, msg.author.id);
+        let chosen = match &*arg {
+            "" => None,
+            "reset" => Some(None),
+            code => match Lang::parse(code) {
+                Some(lang) => Some(Some(lang)),
+                None => {
+                    let vars = [("code", format!("`{}`", code)), ("available", available())];
+                    msg.reply(&*fill(current, "language.unknown", &vars))?;
+                    return Ok(());
+                }
+            },
+        };
+        match chosen {
+            None => fill(
+                current,
+                "language.current",
+                &[("name", text(current, "language.name").to_string()), ("available", available())],
+            ),
+            Some(lang) => match choose(state, msg.author.id, lang) {
+                Ok(()) => {
+                    let now = state.lang(msg.guild_id, msg.author.id);
+                    let key = if lang.is_some() { "language.set" } else { "language.reset" };
+                    fill(now, key, &[("name", text(now, "language.name").to_string())])
+                }
+                Err(why) => fill(current, "error.save_language", &[("why", format!("{:?}", why))]),
+            },
+        }
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+fn choose(state: &State, user: UserId, lang: Option<Lang>) -> Result<(), QueryError> {
+    let meta = Handler::find_user(&state.db, user)?.unwrap_or_else(|| XPMeta::new(Utc::now()));
+    Handler::add_user(
+        &state.db,
+        XPUser {
+            user_id: user,
+            meta: XPMeta {
+                language: lang.map(|l| l.code().to_string()),
+                ..meta
+            },
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_follow_the_language() {
+        assert_eq!(number(Lang::En, 1234.5), "1,234.5");
+        assert_eq!(number(Lang::De, 1234.5), "1.234,5");
+        assert_eq!(number(Lang::Es, 1234.5), "1234,5");
+        assert_eq!(number(Lang::Es, 12345.678), "12.345,68");
+        assert_eq!(number(Lang::En, 7.0), "7");
+        assert_eq!(number(Lang::En, -0.001), "0");
+        assert_eq!(number(Lang::De, 1234567.0), "1.234.567");
+    }
+
+    #[test]
+    fn catalogs_only_use_english_keys() {
+        for lang in ALL {
+            for (key, _) in lang.catalog() {
+                assert!(locales::EN.iter().any(|(k, _)| k == key), "{} has unknown key {}", lang.code(), key);
+            }
+        }
+    }
+}
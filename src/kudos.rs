@@ -0,0 +1,132 @@
+//! `/thanks @user [reason]`: members granting each other xp
+
+use super::{caps, i18n, ledger, rank_up, tracks, Handler, QueryError, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::warn;
+use redis::Commands;
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::{
+    channel::Message,
+    id::{GuildId, UserId},
+};
+
+const DEFAULT_PER_DAY: u64 = 3;
+const DEFAULT_RECEIVED_PER_DAY: u64 = 5;
+/// the daily counters only have to outlive the day they count
+const COUNTER_TTL_SECS: usize = 2 * 24 * 60 * 60;
+
+pub fn thanks(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let lock = ctx.data.lock();
+    let state = lock.get::<State>().expect("Failed to get State");
+    let lang = state.lang(msg.guild_id, msg.author.id);
+    let (target, guild) = match (args.single::<UserId>(), msg.guild_id) {
+        (Ok(target), Some(guild)) => (target, guild),
+        _ => {
+            msg.reply(i18n::text(lang, "kudos.usage"))?;
+            return Ok(());
+        }
+    };
+    let reason = args.rest().trim().to_string();
+    let reply = match give(state, guild, msg, target, &*reason, lang) {
+        Ok(reply) => reply,
+        Err(why) => i18n::fill(lang, "error.thank", &[("why", format!("{:?}", why))]),
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+fn give(
+    state: &State,
+    guild: GuildId,
+    msg: &Message,
+    target: UserId,
+    reason: &str,
+    lang: i18n::Lang,
+) -> Result<String, QueryError> {
+    let giver = msg.author.id;
+    let settings = &state.settings;
+    let amount = match settings.kudos_xp {
+        Some(amount) => amount,
+        None => return Ok(i18n::text(lang, "kudos.disabled").to_string()),
+    };
+    if giver == target {
+        return Ok(i18n::text(lang, "kudos.self").to_string());
+    }
+    if target.to_user().map(|u| u.bot).unwrap_or(false) {
+        return Ok(i18n::text(lang, "kudos.bot").to_string());
+    }
+    let mention = format!("<@!{}>", target.0);
+
+    // per giver: a set of today's targets; per target: a counter across givers,
+    // so a handful of alts can't keep feeding the same member
+    let day = Utc::now().format("%Y-%m-%d");
+    let given_key = format!("kudos:given:{}:{}", giver.0, day);
+    let received_key = format!("kudos:received:{}:{}", target.0, day);
+    let con = state.db.get_connection()?;
+    let given: Vec<u64> = con.smembers(&given_key)?;
+    let per_day = settings.kudos_per_day.unwrap_or(DEFAULT_PER_DAY);
+    if given.contains(&target.0) {
+        return Ok(i18n::fill(lang, "kudos.already", &[("user", mention)]));
+    }
+    if given.len() as u64 >= per_day {
+        return Ok(i18n::fill(lang, "kudos.limit", &[("count", per_day.to_string())]));
+    }
+    let received: Option<u64> = con.get(&received_key)?;
+    if received.unwrap_or(0) >= settings.kudos_received_per_day.unwrap_or(DEFAULT_RECEIVED_PER_DAY) {
+        return Ok(i18n::fill(lang, "kudos.enough", &[("user", mention)]));
+    }
+    let _: () = con.sadd(&given_key, target.0)?;
+    let _: () = con.expire(&given_key, COUNTER_TTL_SECS)?;
+    let _: () = con.incr(&received_key, 1)?;
+    let _: () = con.expire(&received_key, COUNTER_TTL_SECS)?;
+
+    let mut meta = Handler::find_user(&state.db, target)?.unwrap_or_else(|| XPMeta::new(Utc::now()));
+    meta.kudos += 1;
+    meta.see(guild);
+    let main = if settings.main_sources.contains(&tracks::Source::Kudos) {
+        amount
+    } else {
+        0.0
+    };
+    // the tracks get what the main xp would, from the record before add_xp
+    let for_tracks = caps::grantable(amount, &meta, settings, Utc::now());
+    let granted = Handler::add_xp(&state.db, target, &meta, main, settings)?;
+    let note = if reason.is_empty() {
+        format!("kudos from <@!{}>", giver.0)
+    } else {
+        format!("kudos from <@!{}>: {}", giver.0, reason)
+    };
+    ledger::record(&state.db, target, granted, &*note)?;
+    let updated = XPUser {
+        user_id: target,
+        meta: XPMeta {
+            xp: meta.xp + granted,
+            ..meta.clone()
+        },
+    };
+    // the posts are about the member who was thanked, so in their language
+    let their_lang = state.lang(Some(guild), target);
+    let channel = settings.kudos_channel.unwrap_or(msg.channel_id);
+    rank_up(state, guild, &updated, meta.xp, None, channel, msg.timestamp);
+    tracks::award(state, Some(guild), target, tracks::Source::Kudos, for_tracks, channel, msg.timestamp)?;
+
+    if let Some(channel) = settings.kudos_channel {
+        let mut vars = vec![("giver", format!("<@!{}>", giver.0)), ("user", mention.clone())];
+        let key = if reason.is_empty() {
+            "kudos.posted"
+        } else {
+            vars.push(("reason", reason.to_string()));
+            "kudos.posted_reason"
+        };
+        if let Err(why) = channel.say(i18n::fill(their_lang, key, &vars)) {
+            warn!("Failed to post kudos: {:?}", why);
+        }
+    }
+    Ok(i18n::fill(
+        lang,
+        "kudos.done",
+        &[("user", mention), ("xp", i18n::number(lang, granted))],
+    ))
+}
@@ -0,0 +1,33 @@
+//! per-user history of notable xp changes, kept next to the user in redis
+
+use super::QueryError;
+use chrono::prelude::*;
+use redis::Commands;
+use serenity::model::id::UserId;
+
+/// how many entries are kept per user
+const LEDGER_LEN: isize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub at: DateTime<Utc>,
+    pub amount: f64,
+    pub note: String,
+}
+
+fn key(id: UserId) -> String {
+    format!("ledger:{}", id.0)
+}
+
+pub fn record(db: &redis::Client, id: UserId, amount: f64, note: &str) -> Result<(), QueryError> {
+    let con = db.get_connection()?;
+    let entry = Entry {
+        at: Utc::now(),
+        amount,
+        note: note.to_string(),
+    };
+    let _: () = con.lpush(key(id), serde_json::to_string(&entry)?)?;
+    let _: () = con.ltrim(key(id), 0, LEDGER_LEN - 1)?;
+    Ok(())
+}
+
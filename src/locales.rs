@@ -0,0 +1,465 @@
+//! the translation catalogs. english is the reference, every key has to be
+//! in it; the others fall back to it for keys they lack. placeholders are
+//! `{name}` like in the message templates
+
+pub const EN: &[(&str, &str)] = &[
+    ("language.name", "English"),
+    ("language.current", "Your language is **{name}**. Available: {available}. Use `/language <code>` to change it or `/language reset` to follow the server."),
+    ("language.set", "Your language is now **{name}**."),
+    ("language.reset", "You follow the server's language again, **{name}**."),
+    ("language.unknown", "There is no language {code}, use one of {available}."),
+    ("not_in_guild", "You are not in a guild!"),
+    ("guild_not_cached", "Guild is not cached yet, try again shortly."),
+    ("error.grab_users", "Could not grab users ```{why}```"),
+    ("error.update_user", "Could not update user ```{why}```"),
+    ("error.reset_user", "Could not reset user ```{why}```"),
+    ("error.save_ranks", "Could not save ranks ```{why}```"),
+    ("error.save_message", "Could not save message ```{why}```"),
+    ("error.save_language", "Could not save language ```{why}```"),
+    ("error.thank", "Could not thank user ```{why}```"),
+    ("error.prestige", "Could not prestige ```{why}```"),
+    ("title.levelup", "Level up!"),
+    ("title.stats", "Statistics"),
+    ("title.leaderboard", "Leaderboard"),
+    ("title.achievement", "Achievement unlocked!"),
+    ("title.welcome_back", "Welcome back!"),
+    (
+        "template.levelup",
+        "Congratulations {user}, you have just leveled up to rank {rank}{passed}. You need **{xp_needed}** more XP to achieve rank {next_rank}.",
+    ),
+    (
+        "template.levelup_max",
+        "Congratulations {user}, you have just reached rank {rank}{passed} and are at the max level!",
+    ),
+    (
+        "template.stats",
+        "{user} is at rank {rank} with **{xp}** XP (#{position}). **{xp_needed}** more XP to advance to rank {next_rank}.",
+    ),
+    (
+        "template.stats_unranked",
+        "{user} has no rank and **{xp}** XP (#{position}). **{xp_needed}** more XP to advance to rank {next_rank}.",
+    ),
+    ("template.stats_max", "{user} is at the max rank {rank}, with **{xp}** XP (#{position})."),
+    ("template.leaderboard_line", "{medal} - #{position}. {user}{prestige} (level **{level}**, **{xp}** XP)"),
+    ("levelup.passing", " (passing {ranks})"),
+    ("levelup.footer", "You have {xp} XP"),
+    ("stats.unknown_user", "Can't find that user"),
+    ("stats.requirements", "Requirements"),
+    ("stats.needs", "{rank} needs {missing}"),
+    ("stats.allowance", "Allowance"),
+    ("stats.left_today", "**{xp}** XP left today"),
+    ("stats.left_week", "**{xp}** XP left this week"),
+    ("stats.badges", "Badges"),
+    ("stats.kudos", "Kudos"),
+    ("stats.prestige", "Prestige"),
+    ("stats.prestige_level", "{tag} level **{level}**"),
+    ("stats.prestige_bonus", "{tag} level **{level}** (+{percent}% XP)"),
+    ("stats.prestige_none", "none yet"),
+    ("stats.xp", "**{xp}** XP"),
+    ("stats.xp_rank", "**{xp}** XP, rank {rank}"),
+    ("stats.lifetime", "Lifetime"),
+    ("card.level", "Level {level}"),
+    ("card.unranked", "No rank"),
+    ("card.xp", "{xp} / {goal} XP"),
+    ("card.xp_max", "{xp} XP"),
+    ("requirement.guild_days", "**{days}** days in the server ({have} so far)"),
+    ("requirement.account_days", "an account at least **{days}** days old ({have} so far)"),
+    ("requirement.messages", "**{messages}** messages ({have} so far)"),
+    ("requirement.role", "the {role} role"),
+    ("leaderboard.no_track", "There is no track called **{name}**."),
+    ("leaderboard.page", "Page {page} of {pages}"),
+    ("track.reached", "{user} reached {ranks} on the **{track}** track!"),
+    ("reward.reached", "{user} reached rank {rank}!"),
+    ("achievement.unlocked", "{user} unlocked:\n{badges}"),
+    ("welcome_back.text", "Welcome back {user}, you still have **{xp}** XP.{restored}"),
+    ("welcome_back.restored", " Your rank {rank} has been restored."),
+    ("kudos.usage", "Usage: `/thanks @user [reason]`"),
+    ("kudos.disabled", "Kudos are disabled on this server."),
+    ("kudos.self", "You can't thank yourself!"),
+    ("kudos.bot", "Bots don't need thanks."),
+    ("kudos.already", "You already thanked {user} today."),
+    ("kudos.limit", "You can only thank {count} members per day."),
+    ("kudos.enough", "{user} has been thanked enough for today."),
+    ("kudos.posted", "{giver} thanked {user}!"),
+    ("kudos.posted_reason", "{giver} thanked {user}: {reason}"),
+    ("kudos.done", "Thanked {user} (+**{xp}** XP)."),
+    ("prestige.disabled", "Prestige is disabled on this server."),
+    ("prestige.not_top", "You can only prestige at the max rank."),
+    ("prestige.maxed", "You are at the highest prestige already."),
+    ("prestige.bonus", " and earn **{percent}%** more XP"),
+    (
+        "prestige.confirm",
+        "Prestiging resets your **{xp}** XP to zero. You'll get prestige **{level}** {badge}{bonus}. Run `/prestige confirm` to go ahead.",
+    ),
+    ("prestige.done", "You are now prestige **{level}** {badge}!"),
+    (
+        "xp.usage",
+        "Usage: `/xp give|take|set @user <amount> [reason]`, `/xp reset @user|@role|all`, `/xp confirm` or `/xp freeze|unfreeze @user [duration]`",
+    ),
+    ("xp.amount", "The amount must be a non-negative number."),
+    ("xp.adjusted", "{user} now has **{xp}** XP (was **{old}**)."),
+    ("xp.reset_one", "Reset {user} to **0** XP."),
+    (
+        "xp.reset_pending",
+        "This will reset **{count}** members to 0 XP. Run `/xp confirm` within {secs} seconds to go ahead.",
+    ),
+    ("xp.no_pending", "There is no reset waiting for confirmation."),
+    ("xp.expired", "That reset has expired, please run it again."),
+    ("xp.reset_started", "Resetting **{count}** members in the background, the result will be posted here."),
+    ("xp.reset_done", "Reset **{count}** members to 0 XP ({failed} failed)."),
+    (
+        "rank.usage",
+        "Usage: `/rank add @role <xp> [name]`, `/rank remove @role`, `/rank list`, `/rank lock @user @role <duration>`, `/rank unlock @user` or `/ranks sync`",
+    ),
+    ("rank.threshold", "The XP threshold must be a non-negative number."),
+    ("rank.moved", "Moved rank {role} from **{old}** to **{xp}** XP."),
+    ("rank.added", "Added rank {role} at **{xp}** XP."),
+    ("rank.removed", "Removed rank {role}. Members keep the role until it is taken away by hand."),
+    ("rank.not_a_rank", "{role} is not a rank."),
+    ("rank.none", "There are no ranks yet."),
+    ("rank.line", "{role} — **{xp}** XP"),
+    ("rank.line_named", "{role} ({name}) — **{xp}** XP"),
+    ("rank.resync_hint", "Run `/ranks sync` to update every member's roles now."),
+    ("hold.duration_hint", "The duration looks like `30m`, `12h`, `7d` or `2w`, up to a year."),
+    ("hold.frozen", "{user} won't gain XP until {until} UTC."),
+    ("hold.not_frozen", "{user} isn't frozen."),
+    ("hold.unfrozen", "{user} gains XP again."),
+    ("hold.locked", "{user} is locked at {role} until {until} UTC."),
+    ("hold.not_locked", "{user} isn't locked."),
+    ("hold.unlocked", "{user} is no longer locked, their roles follow their XP again."),
+    ("sync.usage", "Usage: `/ranks sync`"),
+    ("sync.started", "Started syncing rank roles, progress is posted below."),
+    ("sync.running", "A sync is already running for this guild."),
+    ("sync.begin", "Syncing the rank roles of **{count}** members…"),
+    ("sync.progress", "Syncing rank roles… {done}/{total}: {summary}"),
+    ("sync.done", "Rank roles synced: {summary}."),
+    (
+        "sync.summary",
+        "checked **{checked}** members, changed **{changed}** (**{added}** roles added, **{removed}** removed), **{absent}** not in the guild",
+    ),
+    (
+        "config.usage",
+        "Usage: `/config message <name> <template>`, `/config message <name> reset`, `/config message <name>` or `/config preview <name> [template]`",
+    ),
+    ("config.names", "Messages: {names}"),
+    ("config.invalid", "That template doesn't work: {reason}."),
+    ("config.reset", "`{name}` is back to its default."),
+    ("config.saved", "Saved `{name}`, it now reads:\n{preview}"),
+    ("config.sample_rank", "**Regular**"),
+    ("config.sample_next_rank", "**Veteran**"),
+    ("config.sample_passed", "**Newcomer**"),
+    ("invalid.empty", "the template is empty"),
+    ("invalid.close", "there is a `}` without a `{`"),
+    ("invalid.open", "there is a `{` without a `}`"),
+    ("invalid.placeholder", "unknown placeholder `{{name}}`, use one of {names}"),
+    ("reload.done", "Reloaded configuration:\n{changes}"),
+    ("reload.invalid", "Not reloading, the configuration is invalid: {why}"),
+];
+
+pub const DE: &[(&str, &str)] = &[
+    ("language.name", "Deutsch"),
+    ("language.current", "Deine Sprache ist **{name}**. Verfügbar: {available}. Mit `/language <code>` änderst du sie, mit `/language reset` folgst du wieder dem Server."),
+    ("language.set", "Deine Sprache ist jetzt **{name}**."),
+    ("language.reset", "Du folgst wieder der Sprache des Servers, **{name}**."),
+    ("language.unknown", "Die Sprache {code} gibt es nicht, verfügbar sind {available}."),
+    ("not_in_guild", "Du bist auf keinem Server!"),
+    ("guild_not_cached", "Der Server ist noch nicht geladen, versuch es gleich noch einmal."),
+    ("error.grab_users", "Konnte die Mitglieder nicht laden ```{why}```"),
+    ("error.update_user", "Konnte das Mitglied nicht aktualisieren ```{why}```"),
+    ("error.reset_user", "Konnte das Mitglied nicht zurücksetzen ```{why}```"),
+    ("error.save_ranks", "Konnte die Ränge nicht speichern ```{why}```"),
+    ("error.save_message", "Konnte die Nachricht nicht speichern ```{why}```"),
+    ("error.save_language", "Konnte die Sprache nicht speichern ```{why}```"),
+    ("error.thank", "Konnte nicht danken ```{why}```"),
+    ("error.prestige", "Prestige fehlgeschlagen ```{why}```"),
+    ("title.levelup", "Aufgestiegen!"),
+    ("title.stats", "Statistiken"),
+    ("title.leaderboard", "Bestenliste"),
+    ("title.achievement", "Erfolg freigeschaltet!"),
+    ("title.welcome_back", "Willkommen zurück!"),
+    (
+        "template.levelup",
+        "Glückwunsch {user}, du bist gerade zum Rang {rank} aufgestiegen{passed}. Dir fehlen noch **{xp_needed}** XP bis zum Rang {next_rank}.",
+    ),
+    (
+        "template.levelup_max",
+        "Glückwunsch {user}, du hast gerade den Rang {rank} erreicht{passed} und bist auf der höchsten Stufe!",
+    ),
+    (
+        "template.stats",
+        "{user} hat den Rang {rank} mit **{xp}** XP (#{position}). Noch **{xp_needed}** XP bis zum Rang {next_rank}.",
+    ),
+    (
+        "template.stats_unranked",
+        "{user} hat noch keinen Rang und **{xp}** XP (#{position}). Noch **{xp_needed}** XP bis zum Rang {next_rank}.",
+    ),
+    ("template.stats_max", "{user} hat den höchsten Rang {rank}, mit **{xp}** XP (#{position})."),
+    ("template.leaderboard_line", "{medal} - #{position}. {user}{prestige} (Stufe **{level}**, **{xp}** XP)"),
+    ("levelup.passing", " (vorbei an {ranks})"),
+    ("levelup.footer", "Du hast {xp} XP"),
+    ("stats.unknown_user", "Dieses Mitglied kenne ich nicht"),
+    ("stats.requirements", "Voraussetzungen"),
+    ("stats.needs", "{rank} braucht {missing}"),
+    ("stats.allowance", "Kontingent"),
+    ("stats.left_today", "heute noch **{xp}** XP"),
+    ("stats.left_week", "diese Woche noch **{xp}** XP"),
+    ("stats.badges", "Abzeichen"),
+    ("stats.kudos", "Dankeschöns"),
+    ("stats.prestige", "Prestige"),
+    ("stats.prestige_level", "{tag} Stufe **{level}**"),
+    ("stats.prestige_bonus", "{tag} Stufe **{level}** (+{percent} % XP)"),
+    ("stats.prestige_none", "noch keins"),
+    ("stats.xp", "**{xp}** XP"),
+    ("stats.xp_rank", "**{xp}** XP, Rang {rank}"),
+    ("stats.lifetime", "Insgesamt"),
+    ("card.level", "Stufe {level}"),
+    ("card.unranked", "Kein Rang"),
+    ("card.xp", "{xp} / {goal} XP"),
+    ("card.xp_max", "{xp} XP"),
+    ("requirement.guild_days", "**{days}** Tage auf dem Server (bisher {have})"),
+    ("requirement.account_days", "ein mindestens **{days}** Tage altes Konto (bisher {have})"),
+    ("requirement.messages", "**{messages}** Nachrichten (bisher {have})"),
+    ("requirement.role", "die Rolle {role}"),
+    ("leaderboard.no_track", "Es gibt keine Rangliste namens **{name}**."),
+    ("leaderboard.page", "Seite {page} von {pages}"),
+    ("track.reached", "{user} hat {ranks} in der Rangliste **{track}** erreicht!"),
+    ("reward.reached", "{user} hat den Rang {rank} erreicht!"),
+    ("achievement.unlocked", "{user} hat freigeschaltet:\n{badges}"),
+    ("welcome_back.text", "Willkommen zurück {user}, du hast noch **{xp}** XP.{restored}"),
+    ("welcome_back.restored", " Dein Rang {rank} wurde wiederhergestellt."),
+    ("kudos.usage", "Verwendung: `/thanks @mitglied [grund]`"),
+    ("kudos.disabled", "Dankeschöns sind auf diesem Server ausgeschaltet."),
+    ("kudos.self", "Du kannst dir nicht selbst danken!"),
+    ("kudos.bot", "Bots brauchen keinen Dank."),
+    ("kudos.already", "Du hast {user} heute schon gedankt."),
+    ("kudos.limit", "Du kannst nur {count} Mitgliedern am Tag danken."),
+    ("kudos.enough", "{user} wurde heute schon genug gedankt."),
+    ("kudos.posted", "{giver} hat {user} gedankt!"),
+    ("kudos.posted_reason", "{giver} hat {user} gedankt: {reason}"),
+    ("kudos.done", "Du hast {user} gedankt (+**{xp}** XP)."),
+    ("prestige.disabled", "Prestige ist auf diesem Server ausgeschaltet."),
+    ("prestige.not_top", "Prestige geht nur auf dem höchsten Rang."),
+    ("prestige.maxed", "Du hast schon das höchste Prestige."),
+    ("prestige.bonus", " und bekommst **{percent} %** mehr XP"),
+    (
+        "prestige.confirm",
+        "Prestige setzt deine **{xp}** XP auf null zurück. Du bekommst Prestige **{level}** {badge}{bonus}. Mit `/prestige confirm` geht es los.",
+    ),
+    ("prestige.done", "Du hast jetzt Prestige **{level}** {badge}!"),
+    (
+        "xp.usage",
+        "Verwendung: `/xp give|take|set @mitglied <menge> [grund]`, `/xp reset @mitglied|@rolle|all`, `/xp confirm` oder `/xp freeze|unfreeze @mitglied [dauer]`",
+    ),
+    ("xp.amount", "Die Menge muss eine nicht negative Zahl sein."),
+    ("xp.adjusted", "{user} hat jetzt **{xp}** XP (vorher **{old}**)."),
+    ("xp.reset_one", "{user} wurde auf **0** XP zurückgesetzt."),
+    (
+        "xp.reset_pending",
+        "Das setzt **{count}** Mitglieder auf 0 XP zurück. Bestätige innerhalb von {secs} Sekunden mit `/xp confirm`.",
+    ),
+    ("xp.no_pending", "Es wartet kein Zurücksetzen auf Bestätigung."),
+    ("xp.expired", "Das Zurücksetzen ist abgelaufen, bitte starte es neu."),
+    ("xp.reset_started", "**{count}** Mitglieder werden im Hintergrund zurückgesetzt, das Ergebnis kommt hierher."),
+    ("xp.reset_done", "**{count}** Mitglieder auf 0 XP zurückgesetzt ({failed} fehlgeschlagen)."),
+    (
+        "rank.usage",
+        "Verwendung: `/rank add @rolle <xp> [name]`, `/rank remove @rolle`, `/rank list`, `/rank lock @mitglied @rolle <dauer>`, `/rank unlock @mitglied` oder `/ranks sync`",
+    ),
+    ("rank.threshold", "Die XP-Schwelle muss eine nicht negative Zahl sein."),
+    ("rank.moved", "Rang {role} von **{old}** auf **{xp}** XP verschoben."),
+    ("rank.added", "Rang {role} bei **{xp}** XP hinzugefügt."),
+    ("rank.removed", "Rang {role} entfernt. Mitglieder behalten die Rolle, bis sie von Hand entfernt wird."),
+    ("rank.not_a_rank", "{role} ist kein Rang."),
+    ("rank.none", "Es gibt noch keine Ränge."),
+    ("rank.line", "{role} — **{xp}** XP"),
+    ("rank.line_named", "{role} ({name}) — **{xp}** XP"),
+    ("rank.resync_hint", "Mit `/ranks sync` werden die Rollen aller Mitglieder sofort aktualisiert."),
+    ("hold.duration_hint", "Die Dauer sieht aus wie `30m`, `12h`, `7d` oder `2w`, höchstens ein Jahr."),
+    ("hold.frozen", "{user} bekommt bis {until} UTC keine XP."),
+    ("hold.not_frozen", "{user} ist nicht eingefroren."),
+    ("hold.unfrozen", "{user} bekommt wieder XP."),
+    ("hold.locked", "{user} ist bis {until} UTC auf {role} festgesetzt."),
+    ("hold.not_locked", "{user} ist nicht festgesetzt."),
+    ("hold.unlocked", "{user} ist nicht mehr festgesetzt, die Rollen folgen wieder den XP."),
+    ("sync.usage", "Verwendung: `/ranks sync`"),
+    ("sync.started", "Die Rangrollen werden abgeglichen, der Fortschritt erscheint unten."),
+    ("sync.running", "Auf diesem Server läuft schon ein Abgleich."),
+    ("sync.begin", "Gleiche die Rangrollen von **{count}** Mitgliedern ab…"),
+    ("sync.progress", "Gleiche Rangrollen ab… {done}/{total}: {summary}"),
+    ("sync.done", "Rangrollen abgeglichen: {summary}."),
+    (
+        "sync.summary",
+        "**{checked}** Mitglieder geprüft, **{changed}** geändert (**{added}** Rollen hinzugefügt, **{removed}** entfernt), **{absent}** nicht auf dem Server",
+    ),
+    (
+        "config.usage",
+        "Verwendung: `/config message <name> <vorlage>`, `/config message <name> reset`, `/config message <name>` oder `/config preview <name> [vorlage]`",
+    ),
+    ("config.names", "Nachrichten: {names}"),
+    ("config.invalid", "Diese Vorlage funktioniert nicht: {reason}."),
+    ("config.reset", "`{name}` ist wieder auf dem Standard."),
+    ("config.saved", "`{name}` gespeichert, so sieht es jetzt aus:\n{preview}"),
+    ("config.sample_rank", "**Stammgast**"),
+    ("config.sample_next_rank", "**Veteran**"),
+    ("config.sample_passed", "**Neuling**"),
+    ("invalid.empty", "die Vorlage ist leer"),
+    ("invalid.close", "da ist eine `}` ohne `{`"),
+    ("invalid.open", "da ist eine `{` ohne `}`"),
+    ("invalid.placeholder", "unbekannter Platzhalter `{{name}}`, erlaubt sind {names}"),
+    ("reload.done", "Konfiguration neu geladen:\n{changes}"),
+    ("reload.invalid", "Nicht neu geladen, die Konfiguration ist ungültig: {why}"),
+];
+
+pub const ES: &[(&str, &str)] = &[
+    ("language.name", "Español"),
+    ("language.current", "Tu idioma es **{name}**. Disponibles: {available}. Usa `/language <código>` para cambiarlo o `/language reset` para seguir al servidor."),
+    ("language.set", "Tu idioma ahora es **{name}**."),
+    ("language.reset", "Vuelves a seguir el idioma del servidor, **{name}**."),
+    ("language.unknown", "No existe el idioma {code}, usa uno de {available}."),
+    ("not_in_guild", "¡No estás en un servidor!"),
+    ("guild_not_cached", "El servidor aún no está cargado, inténtalo de nuevo en un momento."),
+    ("error.grab_users", "No se pudieron cargar los miembros ```{why}```"),
+    ("error.update_user", "No se pudo actualizar al miembro ```{why}```"),
+    ("error.reset_user", "No se pudo reiniciar al miembro ```{why}```"),
+    ("error.save_ranks", "No se pudieron guardar los rangos ```{why}```"),
+    ("error.save_message", "No se pudo guardar el mensaje ```{why}```"),
+    ("error.save_language", "No se pudo guardar el idioma ```{why}```"),
+    ("error.thank", "No se pudo dar las gracias ```{why}```"),
+    ("error.prestige", "No se pudo hacer el prestigio ```{why}```"),
+    ("title.levelup", "¡Subida de nivel!"),
+    ("title.stats", "Estadísticas"),
+    ("title.leaderboard", "Clasificación"),
+    ("title.achievement", "¡Logro desbloqueado!"),
+    ("title.welcome_back", "¡Bienvenido de nuevo!"),
+    (
+        "template.levelup",
+        "¡Enhorabuena {user}, acabas de subir al rango {rank}{passed}! Te faltan **{xp_needed}** XP para llegar al rango {next_rank}.",
+    ),
+    (
+        "template.levelup_max",
+        "¡Enhorabuena {user}, acabas de llegar al rango {rank}{passed} y estás en el nivel máximo!",
+    ),
+    (
+        "template.stats",
+        "{user} tiene el rango {rank} con **{xp}** XP (#{position}). Faltan **{xp_needed}** XP para el rango {next_rank}.",
+    ),
+    (
+        "template.stats_unranked",
+        "{user} aún no tiene rango y tiene **{xp}** XP (#{position}). Faltan **{xp_needed}** XP para el rango {next_rank}.",
+    ),
+    ("template.stats_max", "{user} tiene el rango máximo {rank}, con **{xp}** XP (#{position})."),
+    ("template.leaderboard_line", "{medal} - #{position}. {user}{prestige} (nivel **{level}**, **{xp}** XP)"),
+    ("levelup.passing", " (pasando por {ranks})"),
+    ("levelup.footer", "Tienes {xp} XP"),
+    ("stats.unknown_user", "No encuentro a ese miembro"),
+    ("stats.requirements", "Requisitos"),
+    ("stats.needs", "{rank} requiere {missing}"),
+    ("stats.allowance", "Límite"),
+    ("stats.left_today", "quedan **{xp}** XP hoy"),
+    ("stats.left_week", "quedan **{xp}** XP esta semana"),
+    ("stats.badges", "Insignias"),
+    ("stats.kudos", "Agradecimientos"),
+    ("stats.prestige", "Prestigio"),
+    ("stats.prestige_level", "{tag} nivel **{level}**"),
+    ("stats.prestige_bonus", "{tag} nivel **{level}** (+{percent} % XP)"),
+    ("stats.prestige_none", "todavía ninguno"),
+    ("stats.xp", "**{xp}** XP"),
+    ("stats.xp_rank", "**{xp}** XP, rango {rank}"),
+    ("stats.lifetime", "En total"),
+    ("card.level", "Nivel {level}"),
+    ("card.unranked", "Sin rango"),
+    ("card.xp", "{xp} / {goal} XP"),
+    ("card.xp_max", "{xp} XP"),
+    ("requirement.guild_days", "**{days}** días en el servidor (lleva {have})"),
+    ("requirement.account_days", "una cuenta de al menos **{days}** días (tiene {have})"),
+    ("requirement.messages", "**{messages}** mensajes (lleva {have})"),
+    ("requirement.role", "el rol {role}"),
+    ("leaderboard.no_track", "No hay ninguna clasificación llamada **{name}**."),
+    ("leaderboard.page", "Página {page} de {pages}"),
+    ("track.reached", "¡{user} ha llegado a {ranks} en la clasificación **{track}**!"),
+    ("reward.reached", "¡{user} ha llegado al rango {rank}!"),
+    ("achievement.unlocked", "{user} ha desbloqueado:\n{badges}"),
+    ("welcome_back.text", "Bienvenido de nuevo {user}, todavía tienes **{xp}** XP.{restored}"),
+    ("welcome_back.restored", " Tu rango {rank} ha sido restaurado."),
+    ("kudos.usage", "Uso: `/thanks @miembro [motivo]`"),
+    ("kudos.disabled", "Los agradecimientos están desactivados en este servidor."),
+    ("kudos.self", "¡No puedes darte las gracias a ti mismo!"),
+    ("kudos.bot", "Los bots no necesitan agradecimientos."),
+    ("kudos.already", "Ya le diste las gracias a {user} hoy."),
+    ("kudos.limit", "Solo puedes dar las gracias a {count} miembros al día."),
+    ("kudos.enough", "{user} ya ha recibido suficientes agradecimientos hoy."),
+    ("kudos.posted", "¡{giver} le dio las gracias a {user}!"),
+    ("kudos.posted_reason", "{giver} le dio las gracias a {user}: {reason}"),
+    ("kudos.done", "Le diste las gracias a {user} (+**{xp}** XP)."),
+    ("prestige.disabled", "El prestigio está desactivado en este servidor."),
+    ("prestige.not_top", "Solo puedes hacer prestigio en el rango máximo."),
+    ("prestige.maxed", "Ya tienes el prestigio más alto."),
+    ("prestige.bonus", " y ganarás un **{percent} %** más de XP"),
+    (
+        "prestige.confirm",
+        "El prestigio reinicia tus **{xp}** XP a cero. Obtendrás el prestigio **{level}** {badge}{bonus}. Usa `/prestige confirm` para continuar.",
+    ),
+    ("prestige.done", "¡Ahora tienes el prestigio **{level}** {badge}!"),
+    (
+        "xp.usage",
+        "Uso: `/xp give|take|set @miembro <cantidad> [motivo]`, `/xp reset @miembro|@rol|all`, `/xp confirm` o `/xp freeze|unfreeze @miembro [duración]`",
+    ),
+    ("xp.amount", "La cantidad debe ser un número no negativo."),
+    ("xp.adjusted", "{user} tiene ahora **{xp}** XP (antes **{old}**)."),
+    ("xp.reset_one", "{user} ha vuelto a **0** XP."),
+    (
+        "xp.reset_pending",
+        "Esto reiniciará a **{count}** miembros a 0 XP. Usa `/xp confirm` en menos de {secs} segundos para continuar.",
+    ),
+    ("xp.no_pending", "No hay ningún reinicio esperando confirmación."),
+    ("xp.expired", "Ese reinicio ha caducado, vuelve a lanzarlo."),
+    ("xp.reset_started", "Reiniciando **{count}** miembros en segundo plano, el resultado se publicará aquí."),
+    ("xp.reset_done", "**{count}** miembros reiniciados a 0 XP ({failed} fallidos)."),
+    (
+        "rank.usage",
+        "Uso: `/rank add @rol <xp> [nombre]`, `/rank remove @rol`, `/rank list`, `/rank lock @miembro @rol <duración>`, `/rank unlock @miembro` o `/ranks sync`",
+    ),
+    ("rank.threshold", "El umbral de XP debe ser un número no negativo."),
+    ("rank.moved", "Rango {role} movido de **{old}** a **{xp}** XP."),
+    ("rank.added", "Rango {role} añadido en **{xp}** XP."),
+    ("rank.removed", "Rango {role} eliminado. Los miembros conservan el rol hasta que se quite a mano."),
+    ("rank.not_a_rank", "{role} no es un rango."),
+    ("rank.none", "Todavía no hay rangos."),
+    ("rank.line", "{role} — **{xp}** XP"),
+    ("rank.line_named", "{role} ({name}) — **{xp}** XP"),
+    ("rank.resync_hint", "Usa `/ranks sync` para actualizar ahora los roles de todos los miembros."),
+    ("hold.duration_hint", "La duración es del tipo `30m`, `12h`, `7d` o `2w`, como mucho un año."),
+    ("hold.frozen", "{user} no ganará XP hasta el {until} UTC."),
+    ("hold.not_frozen", "{user} no está congelado."),
+    ("hold.unfrozen", "{user} vuelve a ganar XP."),
+    ("hold.locked", "{user} queda fijado en {role} hasta el {until} UTC."),
+    ("hold.not_locked", "{user} no está fijado."),
+    ("hold.unlocked", "{user} ya no está fijado, sus roles vuelven a seguir su XP."),
+    ("sync.usage", "Uso: `/ranks sync`"),
+    ("sync.started", "Sincronizando los roles de rango, el progreso aparece abajo."),
+    ("sync.running", "Ya hay una sincronización en marcha en este servidor."),
+    ("sync.begin", "Sincronizando los roles de rango de **{count}** miembros…"),
+    ("sync.progress", "Sincronizando roles de rango… {done}/{total}: {summary}"),
+    ("sync.done", "Roles de rango sincronizados: {summary}."),
+    (
+        "sync.summary",
+        "**{checked}** miembros revisados, **{changed}** cambiados (**{added}** roles añadidos, **{removed}** quitados), **{absent}** fuera del servidor",
+    ),
+    (
+        "config.usage",
+        "Uso: `/config message <nombre> <plantilla>`, `/config message <nombre> reset`, `/config message <nombre>` o `/config preview <nombre> [plantilla]`",
+    ),
+    ("config.names", "Mensajes: {names}"),
+    ("config.invalid", "Esa plantilla no funciona: {reason}."),
+    ("config.reset", "`{name}` vuelve a su valor por defecto."),
+    ("config.saved", "`{name}` guardado, ahora queda así:\n{preview}"),
+    ("config.sample_rank", "**Habitual**"),
+    ("config.sample_next_rank", "**Veterano**"),
+    ("config.sample_passed", "**Novato**"),
+    ("invalid.empty", "la plantilla está vacía"),
+    ("invalid.close", "hay una `}` sin `{`"),
+    ("invalid.open", "hay una `{` sin `}`"),
+    ("invalid.placeholder", "marcador desconocido `{{name}}`, usa uno de {names}"),
+    ("reload.done", "Configuración recargada:\n{changes}"),
+    ("reload.invalid", "No se recarga, la configuración no es válida: {why}"),
+];
@@ -5,12 +5,17 @@ extern crate serde_derive;
 extern crate chrono;
 extern crate colored;
 extern crate fern;
+extern crate image;
 extern crate left_pad;
 extern crate log;
 extern crate rand;
 extern crate redis;
 extern crate reqwest;
+extern crate rusttype;
 extern crate serde_json;
+extern crate signal_hook;
+extern crate toml;
+extern crate typemap;
 
 use chrono::prelude::*;
 use log::{error, info, warn};
@@ -19,14 +24,39 @@ use redis::Commands;
 use serenity::client::{Client, Context};
 use serenity::framework::standard::{StandardFramework, CommandError, CommandOptions, Args};
 use serenity::model::{
-    channel::Message,
-    id::{ChannelId, RoleId, UserId},
+    channel::{Message, Reaction},
+    guild::Member,
+    id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+    permissions::Permissions,
+    user::User,
 };
 use serenity::prelude::{EventHandler, TypeMapKey};
-use std::collections::HashSet;
-use std::{cmp, env, fs, hash, num, path, thread};
+use std::collections::{HashMap, HashSet};
+use std::{cmp, env, hash, process, thread};
 
+mod achievements;
+mod admin;
 mod announce;
+mod branding;
+mod caps;
+mod cards;
+mod config;
+mod holds;
+mod i18n;
+mod kudos;
+mod ledger;
+mod locales;
+mod messages;
+mod options;
+mod pages;
+mod prestige;
+mod ranks;
+mod reconcile;
+mod rejoin;
+mod rewards;
+mod reload;
+mod seasons;
+mod tracks;
 
 #[derive(Debug)]
 enum QueryError {
@@ -54,10 +84,11 @@ impl Handler {
             .scan()?
             .collect::<Vec<String>>() // collect to keys (type info needed)
             .iter() // reiterate
-            .map(|key| {
+            .filter_map(|key| key.parse::<u64>().ok().map(|id| (key, id))) // skip ledger & other non-user keys
+            .map(|(key, id)| {
                 let data: String = con.get(&*key)?;
                 Ok(XPUser {
-                    user_id: UserId::from(key.parse::<u64>().expect("Failed to get XPUser from User ID")), // should never fail
+                    user_id: UserId::from(id),
                     meta: serde_json::from_str(&*data)?,
                 })
             }) // turn String into XPUser
@@ -70,6 +101,50 @@ impl Handler {
         Ok(serde_json::from_str(&*data)?)
     }
 
+    /// like `get_user`, but a user without a record is `None` rather than an
+    /// error. for writes that create the record, which must not mistake a
+    /// failed read for a new user and overwrite what is stored
+    fn find_user(db: &redis::Client, id: UserId) -> Result<Option<XPMeta>, QueryError> {
+        let con = db.get_connection()?;
+        let data: Option<String> = con.get(&*(id.0.to_string()))?;
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&*data)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// overwrites the user's (seasonal) xp, creating them if needed. lifetime
+    /// xp moves by the same amount. returns the previous xp
+    fn set_xp(db: &redis::Client, id: UserId, xp: f64) -> Result<f64, QueryError> {
+        let con = db.get_connection()?;
+        let mut meta = Handler::find_user(db, id)?.unwrap_or_else(|| XPMeta::new(Utc::now()));
+        let old = meta.xp;
+        meta.lifetime_xp = Some((meta.lifetime_xp() + xp - old).max(0.0));
+        meta.xp = xp;
+        let _: () = con.set(id.0.to_string(), serde_json::to_string(&meta)?)?;
+        Ok(old)
+    }
+
+    /// keeps `keep` of the user's season xp when a season ends or they
+    /// prestige, and applies `change` to the rest of the record. written
+    /// directly rather than through set_xp, lifetime xp stays as is. the
+    /// record is read right before the write, so nothing awarded since the
+    /// caller looked is lost. returns the xp before and the new record
+    fn reset_season_xp<F: FnOnce(&mut XPMeta)>(
+        db: &redis::Client,
+        id: UserId,
+        keep: f64,
+        change: F,
+    ) -> Result<(f64, XPMeta), QueryError> {
+        let con = db.get_connection()?;
+        let mut meta = Handler::get_user(db, id)?;
+        let old = meta.xp;
+        meta.keep_season_xp(keep);
+        change(&mut meta);
+        let _: () = con.set(id.0.to_string(), serde_json::to_string(&meta)?)?;
+        Ok((old, meta))
+    }
+
     fn add_user(db: &redis::Client, user: XPUser) -> Result<XPMeta, QueryError> {
         let con = db.get_connection()?;
         let id = user.user_id.0.to_string();
@@ -79,19 +154,57 @@ impl Handler {
         Ok(ins_obj)
     }
 
-    fn add_xp(db: &redis::Client, id: UserId, meta: &XPMeta, xp: f64) -> Result<(), QueryError> {
+    /// adds up to `xp` to the user, truncated by the configured caps.
+    /// returns the amount that was actually granted
+    fn add_xp(
+        db: &redis::Client,
+        id: UserId,
+        meta: &XPMeta,
+        xp: f64,
+        settings: &Settings,
+    ) -> Result<f64, QueryError> {
         let con = db.get_connection()?;
+        let now = Utc::now();
+        let (daily, weekly) = caps::window_totals(meta, now);
+        let granted = caps::grantable(xp, meta, settings, now);
+        let xp = xp * prestige::multiplier(meta, settings);
         let new_xp_obj = XPMeta {
-            xp: meta.xp + xp,
-            last_activity: Utc::now(),
+            xp: meta.xp + granted,
+            lifetime_xp: Some(meta.lifetime_xp() + granted),
+            last_activity: now,
+            daily_xp: daily + granted,
+            weekly_xp: weekly + granted,
+            ..meta.clone()
         };
         let obj = serde_json::to_string(&new_xp_obj)?;
-        con.set(id.to_string(), obj)?;
-        Ok(())
+        let _: () = con.set(id.to_string(), obj)?;
+        if granted < xp && !holds::frozen(meta, now) {
+            ledger::record(
+                db,
+                id,
+                granted,
+                &*format!("award of {:.3} XP truncated to {:.3} XP by cap", xp, granted),
+            )?;
+        }
+        Ok(granted)
     }
 }
 
 impl EventHandler for Handler {
+    fn cache_ready(&self, ctx: Context, _: Vec<GuildId>) {
+        reconcile::on_startup(ctx.data.clone());
+    }
+
+    fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        pages::turn(&ctx, &reaction);
+    }
+
+    fn guild_member_addition(&self, ctx: Context, guild_id: GuildId, mut new_member: Member) {
+        let lock = ctx.data.lock();
+        let state = lock.get::<State>().expect("Failed to get State");
+        rejoin::restore(state, guild_id, &mut new_member);
+    }
+
     fn message(&self, ctx: Context, new_message: Message) {
         if !new_message.is_own() {
             // check if user is in database
@@ -100,91 +213,102 @@ impl EventHandler for Handler {
             let lock = ctx.data.lock();
             let state: &State = lock.get::<State>().expect("Failed to get state");
             let db = &state.db;
-            if let Ok(meta) = Handler::get_user(&db, new_message.author.id) {
+            let found = Handler::find_user(&db, new_message.author.id);
+            if let Ok(Some(mut meta)) = found {
+                let mut gained = false;
+                let achievements = &state.settings.achievements;
+                achievements::track(&mut meta, new_message.channel_id, achievements, Utc::now());
+                if let Some(guild) = new_message.guild_id {
+                    meta.see(guild);
+                }
                 if Utc::now().signed_duration_since(meta.last_activity)
-                    > chrono::Duration::seconds(5)
+                    > chrono::Duration::seconds(state.settings.cooldown_secs)
                 {
                     let mut rng = thread_rng();
-                    let xp = rng.gen_range(0.3, 0.5);
-                    let res = Handler::add_xp(&db, new_message.author.id, &meta, xp).is_ok();
-                    if res {
+                    let (min, max) = (state.settings.xp_min, state.settings.xp_max);
+                    let rolled = if max > min { rng.gen_range(min, max) } else { min };
+                    // still goes through add_xp when messages don't feed the
+                    // main xp, so the cooldown and counters are saved
+                    let xp = if state.settings.main_sources.contains(&tracks::Source::Messages) {
+                        rolled
+                    } else {
+                        0.0
+                    };
+                    // the tracks get what the main xp would, from the record
+                    // before add_xp updates the caps
+                    let for_tracks = caps::grantable(rolled, &meta, &state.settings, Utc::now());
+                    let res =
+                        Handler::add_xp(&db, new_message.author.id, &meta, xp, &state.settings);
+                    // after add_xp, which writes the whole record
+                    let awarded = tracks::award(
+                        state,
+                        new_message.guild_id,
+                        new_message.author.id,
+                        tracks::Source::Messages,
+                        for_tracks,
+                        new_message.channel_id,
+                        new_message.timestamp,
+                    );
+                    if let Err(why) = awarded {
+                        error!("Failed to add track xp! {:?}", why);
+                    }
+                    if let Ok(xp) = res {
                         info!(
                             "Successfully added {} xp to {}",
                             xp, new_message.author.name
                         );
-                        // check if this was a level up
-                        let alpha = state
-                            .ranks
-                            .clone()
-                            .into_iter()
-                            .filter(|r| meta.xp + xp >= r.required_xp)
-                            .collect::<HashSet<Rank>>();
-                        let beta = state
-                            .ranks
-                            .clone()
-                            .into_iter()
-                            .filter(|r| meta.xp < r.required_xp)
-                            .collect::<HashSet<Rank>>();
-                        let mut intersect = alpha.intersection(&beta);
-                        if let Some(rank) = intersect.next() {
-                            let xp_usr = XPUser {
+                        gained = xp > 0.0;
+                        if let Some(guild) = new_message.guild_id {
+                            let updated = XPUser {
                                 user_id: new_message.author.id,
                                 meta: XPMeta {
                                     xp: meta.xp + xp,
-                                    ..meta
+                                    ..meta.clone()
                                 },
                             };
-                            let role = rank.role_id;
-                            let cached = role.to_role_cached();
-                            info!("{:?}", cached);
-                            if let Some(a) = cached {
-                                info!("{:?}", a);
-                                info!("{:?}", a.find_guild());
-                            }
-                            if let Some(mut memb) = new_message.member() {
-                                // remove all roles we are !!not!!
-                                info!(
-                                    "removing roles: {:?}",
-                                    memb.remove_roles(
-                                        state
-                                            .ranks
-                                            .clone()
-                                            .into_iter()
-                                            .filter(|r| r != rank)
-                                            .map(|r| r.role_id)
-                                            .collect::<Vec<serenity::model::id::RoleId>>()
-                                            .as_slice()
-                                    )
-                                );
-                                info!("adding role: {:?}", memb.add_role(role));
-                                let embed = new_message.channel_id.send_message(|_| {
-                                    create_level_up_embed(
-                                        xp_usr,
-                                        state.ranks.clone(),
-                                        new_message.timestamp,
-                                        new_message.author.avatar_url(),
-                                    )
+                            let posted = rank_up(
+                                state,
+                                guild,
+                                &updated,
+                                meta.xp,
+                                None,
+                                new_message.channel_id,
+                                new_message.timestamp,
+                            );
+                            if let Some(embed) = posted {
+                                thread::spawn(move || {
+                                    thread::sleep(std::time::Duration::from_millis(15000));
+                                    info!("{:?}", embed.delete());
                                 });
-                                if let Ok(embed) = embed {
-                                    thread::spawn(move || {
-                                        thread::sleep(std::time::Duration::from_millis(15000));
-                                        info!("{:?}", embed.delete());
-                                    });
-                                }
                             }
                         }
                     } else {
                         error!("Failed to add xp! {:?}", res);
                     }
+                } else {
+                    // no xp this time, but the counters still need saving: the
+                    // `messages` requirement counts every message, not only
+                    // those that gave xp
+                    if let Err(why) = Handler::add_user(
+                        &db,
+                        XPUser {
+                            user_id: new_message.author.id,
+                            meta,
+                        },
+                    ) {
+                        error!("Failed to save counters! {:?}", why);
+                    }
                 }
-            } else {
+                achievements::check(state, &new_message, gained);
+            } else if let Ok(None) = found {
                 // if user is not in database, create them and attribute xp
+                let mut meta = XPMeta::new(Utc::now());
+                if let Some(guild) = new_message.guild_id {
+                    meta.see(guild);
+                }
                 let new = XPUser {
                     user_id: new_message.author.id,
-                    meta: XPMeta {
-                        last_activity: Utc::now(),
-                        xp: 0.0,
-                    },
+                    meta,
                 };
                 let res = Handler::add_user(&db, new);
                 if res.is_ok() {
@@ -192,6 +316,8 @@ impl EventHandler for Handler {
                 } else {
                     error!("WTF! Couldn't add user {:?}", res);
                 }
+            } else {
+                error!("Failed to read user {}: {:?}", new_message.author.id, found);
             }
         }
     }
@@ -203,49 +329,119 @@ struct XPUser {
     meta: XPMeta,
 }
 
-impl XPUser {
-    fn achieved(&self, ranks: &Vec<Rank>) -> Vec<Rank> {
-        ranks
-            .clone()
-            .into_iter()
-            .filter(|r| self.meta.xp >= r.required_xp)
-            .collect::<Vec<Rank>>()
-    }
-    fn left(&self, ranks: &Vec<Rank>) -> Vec<Rank> {
-        ranks
-            .clone()
-            .into_iter()
-            .filter(|r| self.meta.xp < r.required_xp)
-            .collect::<Vec<Rank>>()
-    }
-    fn level(&self, ranks: &[Rank]) -> Option<Rank> {
-        let a = self.achieved(&ranks.to_vec());
-        if a.is_empty() {
-            None
-        } else {
-            Some(a[a.len()-1].clone())
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ReifiedXPUser {
-    xp_user: XPUser,
-    username: String,
-    discriminator: u16,
-    level: usize,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct XPMeta {
+    /// xp in the current season
     xp: f64,
+    /// xp across all seasons, missing for users from before seasons existed
+    #[serde(default)]
+    lifetime_xp: Option<f64>,
     last_activity: DateTime<Utc>,
+    /// xp awarded on the day of `last_activity`
+    #[serde(default)]
+    daily_xp: f64,
+    /// xp awarded in the iso week of `last_activity`
+    #[serde(default)]
+    weekly_xp: f64,
+    /// times this user was thanked with `/thanks`
+    #[serde(default)]
+    kudos: u64,
+    #[serde(default)]
+    messages: u64,
+    /// consecutive days with at least one message, ending on `streak_day`
+    #[serde(default)]
+    streak: u32,
+    #[serde(default)]
+    streak_day: Option<NaiveDate>,
+    /// message counts in channels that achievements look at
+    #[serde(default)]
+    channel_messages: HashMap<u64, u64>,
+    /// ids of unlocked achievements
+    #[serde(default)]
+    badges: Vec<String>,
+    /// xp on the extra rank tracks, by track key
+    #[serde(default)]
+    tracks: HashMap<String, f64>,
+    /// times the user traded their xp for prestige
+    #[serde(default)]
+    prestige: u32,
+    /// no xp is gained before this, set by `/xp freeze`
+    #[serde(default)]
+    frozen_until: Option<DateTime<Utc>>,
+    /// rank locks by guild, set by `/rank lock`
+    #[serde(default)]
+    locks: HashMap<u64, holds::RankLock>,
+    /// language code chosen with `/language`, the guild's is used if unset
+    #[serde(default)]
+    language: Option<String>,
+    /// guilds the user was seen in, missing for users from before it was kept
+    #[serde(default)]
+    guilds: Option<Vec<u64>>,
 }
 
-#[derive(Debug, Clone)]
+impl XPMeta {
+    fn new(now: DateTime<Utc>) -> XPMeta {
+        XPMeta {
+            xp: 0.0,
+            lifetime_xp: Some(0.0),
+            last_activity: now,
+            daily_xp: 0.0,
+            weekly_xp: 0.0,
+            kudos: 0,
+            messages: 0,
+            streak: 0,
+            streak_day: None,
+            channel_messages: HashMap::new(),
+            badges: Vec::new(),
+            tracks: HashMap::new(),
+            prestige: 0,
+            frozen_until: None,
+            locks: HashMap::new(),
+            language: None,
+            guilds: Some(Vec::new()),
+        }
+    }
+
+    fn track_xp(&self, track: &str) -> f64 {
+        self.tracks.get(track).cloned().unwrap_or(0.0)
+    }
+
+    fn lifetime_xp(&self) -> f64 {
+        self.lifetime_xp.unwrap_or(self.xp)
+    }
+
+    /// keeps `keep` of the season xp. lifetime xp is pinned first, users from
+    /// before seasons would lose it along with their xp
+    fn keep_season_xp(&mut self, keep: f64) {
+        self.lifetime_xp = Some(self.lifetime_xp());
+        self.xp *= keep;
+    }
+
+    /// notes that the user is a member of `guild`
+    fn see(&mut self, guild: GuildId) {
+        let guilds = self.guilds.get_or_insert_with(Vec::new);
+        if !guilds.contains(&guild.0) {
+            guilds.push(guild.0);
+        }
+    }
+
+    /// users from before guilds were kept count as seen everywhere
+    fn seen_in(&self, guild: GuildId) -> bool {
+        self.guilds.as_ref().map(|g| g.contains(&guild.0)).unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Rank {
     role_id: RoleId,
     required_xp: f64,
+    name: Option<String>,
+    /// checked on top of the xp when promoting
+    #[serde(default)]
+    requires: ranks::Requirements,
+    /// extra actions when the rank is reached
+    #[serde(default)]
+    rewards: rewards::Rewards,
 }
 
 impl cmp::PartialEq for Rank {
@@ -260,30 +456,117 @@ impl hash::Hash for Rank {
     }
 }
 
-enum ParseError {
-    Float(num::ParseFloatError),
-    Int(num::ParseIntError),
+/// runtime knobs resolved from the config file
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    xp_min: f64,
+    xp_max: f64,
+    cooldown_secs: i64,
+    daily_cap: Option<f64>,
+    weekly_cap: Option<f64>,
+    /// permission bits required for the `/xp` commands
+    admin_permission: Option<u64>,
+    /// seasons are disabled unless a length is set
+    season_length_days: Option<i64>,
+    season_start: Option<DateTime<Utc>>,
+    /// fraction of xp kept when a season ends, no reset if unset
+    season_keep: Option<f64>,
+    /// (top n, role) pairs handed out when a season ends
+    season_rewards: Vec<(usize, RoleId)>,
+    /// xp granted by `/thanks`, kudos are disabled unless set
+    kudos_xp: Option<f64>,
+    /// thanks a member may give per day
+    kudos_per_day: Option<u64>,
+    /// thanks a member may receive per day, across all givers
+    kudos_received_per_day: Option<u64>,
+    kudos_channel: Option<ChannelId>,
+    achievements: Vec<achievements::Achievement>,
+    branding: config::Branding,
+    /// award sources feeding the main xp
+    main_sources: Vec<tracks::Source>,
+    /// rank tracks next to the main one, for guilds without their own
+    tracks: Vec<tracks::Track>,
+    /// tracks of guilds that have their own
+    guild_tracks: HashMap<GuildId, Vec<tracks::Track>>,
+    /// `/prestige` is disabled unless set
+    prestige: Option<prestige::Prestige>,
 }
 
-impl From<num::ParseFloatError> for ParseError {
-    fn from(pfe: num::ParseFloatError) -> ParseError {
-        ParseError::Float(pfe)
+impl Settings {
+    fn admin_permissions(&self) -> Permissions {
+        self.admin_permission
+            .map(Permissions::from_bits_truncate)
+            .unwrap_or(Permissions::ADMINISTRATOR)
     }
 }
 
-impl From<num::ParseIntError> for ParseError {
-    fn from(pfe: num::ParseIntError) -> ParseError {
-        ParseError::Int(pfe)
+impl State {
+    fn ranks_for(&self, guild: Option<GuildId>) -> &Vec<Rank> {
+        guild
+            .and_then(|g| self.guild_ranks.get(&g))
+            .unwrap_or(&self.ranks)
     }
-}
 
-impl Rank {
-    fn from(s: String) -> Result<Rank, ParseError> {
-        let data: Vec<String> = s.split_whitespace().map(String::from).collect();
-        Ok(Rank {
-            role_id: RoleId::from(data[0].parse::<u64>()?),
-            required_xp: data[1].parse::<f64>()?,
-        })
+    fn rank_mode(&self, guild: GuildId) -> ranks::RankMode {
+        self.config.rank_mode(guild)
+    }
+
+    /// the embed branding of a guild, the global one outside guilds
+    fn style(&self, guild: Option<GuildId>) -> branding::Style {
+        branding::Style::resolve(
+            &self.settings.branding,
+            guild.and_then(|g| self.config.branding_for(g)),
+            guild,
+        )
+    }
+
+    /// the member's own language, else the guild's, else english
+    fn lang(&self, guild: Option<GuildId>, user: UserId) -> i18n::Lang {
+        Handler::get_user(&self.db, user)
+            .ok()
+            .and_then(|meta| meta.language)
+            .and_then(|code| i18n::Lang::parse(&*code))
+            .or_else(|| guild.and_then(|g| self.config.language_for(g)))
+            .unwrap_or_default()
+    }
+
+    /// the guild's own rank tracks, else the global ones
+    fn tracks_for(&self, guild: Option<GuildId>) -> &[tracks::Track] {
+        guild
+            .and_then(|g| self.settings.guild_tracks.get(&g))
+            .unwrap_or(&self.settings.tracks)
+    }
+
+    /// how embeds about a member with `xp` are written in `guild`, in `lang`,
+    /// stamped `at`. `keys` are the templates the embed picks from; the
+    /// position takes a scan of every user, so it is only looked up if one
+    /// of them shows it
+    fn rendering(
+        &self,
+        guild: Option<GuildId>,
+        lang: i18n::Lang,
+        xp: f64,
+        at: DateTime<FixedOffset>,
+        keys: &[&str],
+    ) -> Rendering {
+        let templates = messages::templates(self, guild, lang);
+        let position = if templates.shows(keys, "position") {
+            messages::position(self, xp)
+        } else {
+            None
+        };
+        Rendering {
+            style: self.style(guild),
+            templates,
+            lang,
+            position,
+            at,
+        }
+    }
+
+    /// how the guild's rank cards look, none if cards aren't set up
+    fn card_look(&self, guild: Option<GuildId>) -> Option<cards::Look> {
+        cards::Look::resolve(self.config.cards.as_ref(), guild.and_then(|g| self.config.cards_for(g)))
     }
 }
 
@@ -291,7 +574,7 @@ impl TypeMapKey for State {
     type Value = State;
 }
 
-fn setup_logger() -> Result<(), fern::InitError> {
+fn setup_logger(logging: &config::Logging, level: log::LevelFilter) -> Result<(), fern::InitError> {
     use colored::Colorize;
     fn colorize_format(level: log::Level) -> String {
         fn color_for_level(level: log::Level) -> colored::Color {
@@ -324,9 +607,9 @@ fn setup_logger() -> Result<(), fern::InitError> {
                 message
             ))
         })
-        .level(log::LevelFilter::Debug)
+        .level(level)
         .chain(std::io::stdout())
-        .chain(fern::log_file("output.log")?)
+        .chain(fern::log_file(&logging.file)?)
         // suppress hyper, tokio & serenity
         .level_for("tokio_reactor", log::LevelFilter::Off)
         .level_for("hyper", log::LevelFilter::Info)
@@ -337,133 +620,361 @@ fn setup_logger() -> Result<(), fern::InitError> {
 
 #[derive(Debug, Clone)]
 struct State {
+    options: options::Options,
+    config: config::Config,
+    /// ranks from the config, used by guilds without ranks of their own
     ranks: Vec<Rank>,
+    /// ranks managed with `/rank`, by guild
+    guild_ranks: HashMap<GuildId, Vec<Rank>>,
+    settings: Settings,
     db: redis::Client,
+    /// bulk resets waiting for `/xp confirm`, by the admin who asked
+    pending_resets: HashMap<UserId, admin::PendingReset>,
+    /// guilds with a running `/ranks sync`
+    sync_jobs: HashSet<GuildId>,
+    /// avatars drawn on rank cards and leaderboard images
+    avatars: cards::Avatars,
+    /// posted leaderboards whose pages can still be turned, by message
+    boards: HashMap<MessageId, pages::Board>,
 }
 
 fn main() -> Result<(), std::io::Error> {
-    use std::io::Read;
-
-    setup_logger().expect("Failed to setup logger");
+    let options = match options::Options::parse(env::vars(), env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", options::USAGE);
+            return Ok(());
+        }
+        Err(why) => {
+            eprintln!("{}\n\n{}", why, options::USAGE);
+            process::exit(2);
+        }
+    };
+    let loaded = match config::Config::load(&*options.config_path, &options.overrides) {
+        Ok(loaded) => loaded,
+        Err(why) => {
+            eprintln!("Invalid configuration in {}: {}", options.config_path, why);
+            process::exit(1);
+        }
+    };
+    let token = match options.token {
+        Some(ref token) => token.clone(),
+        None => {
+            eprintln!("No token given, set DISCORD_TOKEN or use --token / --token-file");
+            process::exit(1);
+        }
+    };
 
-    let config_file = "config.txt";
+    setup_logger(&loaded.config.logging, loaded.config.log_level())
+        .expect("Failed to setup logger");
 
-    // check if config file exists
-    if !path::Path::new(config_file).exists() {
-        fs::File::create(config_file)?;
+    info!("Using {} (token: <redacted>)", options.config_path);
+    match loaded.config.redacted() {
+        Ok(effective) => info!("Effective configuration:\n{}", effective),
+        Err(why) => warn!("Could not print configuration: {:?}", why),
     }
 
-    // read config
-    let mut buf = String::new();
-    fs::File::open(config_file)?.read_to_string(&mut buf)?;
-    let mut iter = buf.split('\n');
-    if let Some(guild_str) = iter.next() {
-        let guild = guild_str.parse::<u64>().expect("Failed to parse guild");
-        let ranks: Vec<Rank> = iter
-            .map(String::from)
-            .filter(|s| !s.is_empty())
-            .map(Rank::from)
-            .filter_map(Result::ok)
-            .collect();
-        
+    info!(
+        "Serving guilds {:?} ({} ranks)",
+        loaded.config.guilds(),
+        loaded.ranks.len()
+    );
+
+    let redis_client = {
+        redis::Client::open(&*loaded.config.storage.url).expect("Failed to connect to redis")
+    };
 
-        info!("Serving only guild {} ({} ranks)", guild, ranks.len());
+    let mut guild_ranks = HashMap::new();
+    for guild in loaded.config.guilds() {
+        match ranks::load_guild_ranks(&redis_client, guild) {
+            Ok(Some(ranks)) => {
+                info!("Guild {} manages its own ranks ({} ranks)", guild, ranks.len());
+                guild_ranks.insert(guild, ranks);
+            }
+            Ok(None) => {}
+            Err(why) => error!("Failed to load ranks of guild {}: {:?}", guild, why),
+        }
+    }
 
-        let redis_client =
-            { redis::Client::open("redis://127.0.0.1").expect("Failed to connect to redis") };
+    let state = State {
+        options: options.clone(),
+        config: loaded.config,
+        ranks: loaded.ranks,
+        guild_ranks,
+        settings: loaded.settings,
+        db: redis_client,
+        pending_resets: HashMap::new(),
+        sync_jobs: HashSet::new(),
+        avatars: cards::Avatars::default(),
+        boards: HashMap::new(),
+    };
+    let admin_permissions = state.settings.admin_permissions();
 
-        let state = State {
-            ranks,
-            db: redis_client,
-        };
+    let mut client = Client::new(&token, Handler)
+        .expect("Error creating client");
+    {
+        let mut data = client.data.lock();
+        data.insert::<State>(state);
+    }
 
-        let mut client = Client::new(&env::var("DISCORD_TOKEN").expect("token"), Handler)
-            .expect("Error creating client");
-        {
-            let mut data = client.data.lock();
-            data.insert::<State>(state);
+    if let Err(why) = reload::watch(client.data.clone(), options.config_path.clone()) {
+        error!("Could not watch for configuration changes: {:?}", why);
+    }
+
+    // periodic jobs
+    let data = client.data.clone();
+    thread::spawn(move || loop {
+        thread::sleep(std::time::Duration::from_secs(60));
+        let state = data.lock().get::<State>().cloned();
+        if let Some(state) = state {
+            seasons::tick(&state);
+            holds::tick(&state);
         }
+    });
 
-        client.with_framework(
-            StandardFramework::new()
-                .configure(|c| c.prefix("/"))
-                .on("leaderboard", |ctx, msg, args| {
-                    if let Some(chan) = msg.channel() {
-                        let c = chan.id();
-                        let lock = ctx.data.lock();
-                        let state: &State = lock.get::<State>().expect("Failed to get State");
-                        let result = Handler::get_users(&state.db);
-                        if let Ok(users) = result {
-                            info!("{:?}", args);
-                            c.send_message(|_| {
-                                create_leaderboard_embed(
-                                    users,
-                                    state.ranks.clone(),
-                                    msg.timestamp,
-                                    args.current()
-                                        .map(|x| x.parse::<usize>().unwrap_or(5))
-                                        .unwrap_or(5),
-                                )
-                            }).expect("Failed to send message");
-                        } else {
-                            msg.reply(&*format!("Could not grab users ```{:?}```", result))
-                                .expect("Failed to send message");
+    client.with_framework(
+        StandardFramework::new()
+            .configure(|c| c.prefix("/"))
+            .on("leaderboard", |ctx, msg, args| {
+                if let Some(chan) = msg.channel() {
+                    let c = chan.id();
+                    let lock = ctx.data.lock();
+                    let state: &State = lock.get::<State>().expect("Failed to get State");
+                    let lang = state.lang(msg.guild_id, msg.author.id);
+                    info!("{:?}", args);
+                    let mut season = None;
+                    let mut track = None;
+                    let mut size = pages::PAGE_SIZE;
+                    let mut page = 0;
+                    let mut image = false;
+                    for arg in args.full().split_whitespace() {
+                        if arg == "image" {
+                            image = true;
+                            continue;
+                        }
+                        if let Some(name) = tracks::parse_arg(arg) {
+                            track = Some(name.to_string());
+                            continue;
+                        }
+                        if let Some(n) = pages::parse_arg(arg) {
+                            page = n - 1;
+                            continue;
                         }
+                        match seasons::parse_arg(arg) {
+                            Some(n) => season = Some(n),
+                            None => size = arg.parse::<usize>().unwrap_or(pages::PAGE_SIZE),
+                        }
+                    }
+                    let track = match track {
+                        Some(ref name) if name != tracks::MAIN => {
+                            match tracks::find(state.tracks_for(msg.guild_id), name) {
+                                Some(track) => Some(track),
+                                None => {
+                                    msg.reply(&*i18n::fill(lang, "leaderboard.no_track", &[("name", name.clone())]))
+                                        .expect("Failed to send message");
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        _ => None,
+                    };
+                    let result = match season {
+                        Some(n) => seasons::archived(&state.db, n)
+                            .map(|s| s.map(|s| s.users()).unwrap_or_default()),
+                        None => Handler::get_users(&state.db),
+                    };
+                    // a track's leaderboard is the main one with the track's xp
+                    let (result, ranks) = match track {
+                        Some(track) => (
+                            result.map(|users| {
+                                users
+                                    .into_iter()
+                                    .map(|u| XPUser {
+                                        meta: track.view(&u.meta),
+                                        ..u
+                                    })
+                                    .collect()
+                            }),
+                            track.ranks.clone(),
+                        ),
+                        None => (result, state.ranks_for(msg.guild_id).clone()),
+                    };
+                    if let Ok(users) = result {
+                        let board = pages::Board::new(users, ranks, size, page, msg.guild_id, lang, msg.timestamp);
+                        let look = state.card_look(msg.guild_id).filter(|_| image);
+                        // the image downloads avatars, so both replies are made
+                        // from copies without holding the data lock
+                        let avatars = state.avatars.clone();
+                        let settings = state.settings.clone();
+                        let style = state.style(msg.guild_id);
+                        let templates = messages::templates(state, msg.guild_id, lang);
+                        drop(lock);
+                        if let Some(look) = look {
+                            // the cache spares most of the requests for who is on the page
+                            let top: Vec<(usize, User, f64)> = board
+                                .shown()
+                                .take(cards::MAX_ROWS)
+                                .filter_map(|(position, u)| {
+                                    u.user_id
+                                        .to_user_cached()
+                                        .map(|user| user.read().clone())
+                                        .or_else(|| u.user_id.to_user().ok())
+                                        .map(|user| (position, user, u.meta.xp))
+                                })
+                                .collect();
+                            let sent = cards::render_leaderboard(&look, &avatars, &top, &board.ranks, msg.guild_id, lang)
+                                .map_err(|why| format!("{:?}", why))
+                                .and_then(|png| {
+                                    c.send_files(vec![(&png[..], "leaderboard.png")], |m| m)
+                                        .map_err(|why| format!("{:?}", why))
+                                });
+                            match sent {
+                                Ok(_) => return Ok(()),
+                                Err(why) => warn!("Failed to send a leaderboard image, sending the embed: {}", why),
+                            }
+                        }
+                        let sent = c
+                            .send_message(|m| {
+                                m.embed(|e| create_leaderboard_embed(e, &board, &settings, &style, &templates))
+                            })
+                            .expect("Failed to send message");
+                        pages::watch(ctx.data.clone(), &sent, board);
                     } else {
-                        msg.reply("You are not in a guild!")
+                        msg.reply(&*i18n::fill(lang, "error.grab_users", &[("why", format!("{:?}", result))]))
                             .expect("Failed to send message");
                     }
-                    Ok(())
-                })
-                .on("stats", |ctx, msg, mut args| {
-                    fn parse(
-                        args: &mut serenity::framework::standard::Args,
-                        msg: &Message,
-                    ) -> (UserId, bool, Option<String>) {
-                        let arg = args.single::<UserId>();
-                        if arg.is_ok() {
-                            let user = arg.unwrap();
-                            if let Ok(user_obj) = user.to_user() {
-                                return (user, false, user_obj.avatar_url());
-                            }
+                } else {
+                    msg.reply(i18n::text(i18n::Lang::default(), "not_in_guild"))
+                        .expect("Failed to send message");
+                }
+                Ok(())
+            })
+            .on("stats", |ctx, msg, mut args| {
+                fn parse(
+                    args: &mut serenity::framework::standard::Args,
+                    msg: &Message,
+                ) -> User {
+                    let arg = args.single::<UserId>();
+                    if arg.is_ok() {
+                        let user = arg.unwrap();
+                        if let Ok(user_obj) = user.to_user() {
+                            return user_obj;
                         }
-                        (msg.author.id, true, msg.author.avatar_url())
                     }
+                    msg.author.clone()
+                }
 
-                    let (des_user, myself, avatar) = parse(&mut args, &msg);
+                let des = parse(&mut args, &msg);
+                let des_user = des.id;
+                let avatar = des.avatar_url();
+                // `card` or `text` picks the reply over the guild's default
+                let style = args.rest().trim().to_lowercase();
+                let chan = match msg.channel() {
+                    Some(chan) => chan.id(),
+                    None => return Ok(()),
+                };
+                // a card downloads the avatar, so it is drawn from copies
+                // without holding the data lock
+                let card = {
                     let lock = ctx.data.lock();
                     let state: &State = lock.get::<State>().expect("Failed to get State");
-                    if let Some(chan) = msg.channel() {
-                        if let Ok(user) = Handler::get_user(&state.db, des_user) {
-                            chan.id()
-                                .send_message(|_| {
-                                    create_info_embed(
-                                        XPUser {
-                                            user_id: des_user,
-                                            meta: user,
-                                        },
-                                        &state.ranks,
-                                        msg.timestamp,
-                                        myself,
-                                        avatar,
-                                    )
-                                })
-                                .expect("Failed to send message");
-                        } else {
-                            msg.reply("Can't find that user")
-                                .expect("Failed to send message");
+                    let look = state
+                        .card_look(msg.guild_id)
+                        .filter(|look| style == "card" || (look.default && style != "text"));
+                    let found = Handler::get_user(&state.db, des_user);
+                    match (look, found) {
+                        (Some(look), Ok(user)) => {
+                            let lang = state.lang(msg.guild_id, msg.author.id);
+                            let mut rendering = state.rendering(msg.guild_id, lang, user.xp, msg.timestamp, &[]);
+                            // the card always shows the position
+                            rendering.position = messages::position(state, user.xp);
+                            let ranks = state.ranks_for(msg.guild_id).clone();
+                            Some((look, state.avatars.clone(), user.xp, ranks, rendering))
                         }
+                        _ => None,
                     }
-                    Ok(())
-                })
-                .command("announce", |c| c.required_permissions(serenity::model::permissions::Permissions::ADMINISTRATOR).exec(announce))
-        );
+                };
+                if let Some((look, avatars, xp, ranks, rendering)) = card {
+                    let sent = cards::render(&look, &avatars, &des, xp, &ranks, msg.guild_id, &rendering)
+                        .map_err(|why| format!("{:?}", why))
+                        .and_then(|png| {
+                            chan.send_files(vec![(&png[..], "rank.png")], |m| m)
+                                .map_err(|why| format!("{:?}", why))
+                        });
+                    match sent {
+                        Ok(_) => return Ok(()),
+                        Err(why) => warn!("Failed to send a rank card, sending the embed: {}", why),
+                    }
+                }
+                let lock = ctx.data.lock();
+                let state: &State = lock.get::<State>().expect("Failed to get State");
+                let lang = state.lang(msg.guild_id, msg.author.id);
+                if let Ok(user) = Handler::get_user(&state.db, des_user) {
+                    let ranks = state.ranks_for(msg.guild_id);
+                    let rendering = state.rendering(msg.guild_id, lang, user.xp, msg.timestamp, messages::STATS);
+                    let blocked = msg
+                        .guild_id
+                        .and_then(|guild| guild.member(des_user).ok())
+                        .map(|member| {
+                            ranks::blocked(ranks, &member, &user, Utc::now())
+                                .into_iter()
+                                .map(|(rank, missing)| {
+                                    let missing = missing.iter().map(|m| m.describe(lang)).collect::<Vec<String>>();
+                                    i18n::fill(
+                                        lang,
+                                        "stats.needs",
+                                        &[("rank", format!("<@&{}>", rank.role_id.0)), ("missing", missing.join(", "))],
+                                    )
+                                })
+                                .collect::<Vec<String>>()
+                        })
+                        .unwrap_or_default();
+                    chan.send_message(|_| {
+                        create_info_embed(
+                            XPUser {
+                                user_id: des_user,
+                                meta: user,
+                            },
+                            ranks,
+                            state.tracks_for(msg.guild_id),
+                            &blocked,
+                            &state.settings,
+                            &rendering,
+                            avatar,
+                        )
+                    })
+                    .expect("Failed to send message");
+                } else {
+                    msg.reply(i18n::text(lang, "stats.unknown_user"))
+                        .expect("Failed to send message");
+                }
+                Ok(())
+            })
+            .command("announce", |c| c.required_permissions(Permissions::ADMINISTRATOR).exec(announce))
+            .command("xp", |c| c.required_permissions(admin_permissions).exec(admin::xp))
+            .command("reload", |c| c.required_permissions(admin_permissions).exec(reload::command))
+            .command("rank", |c| {
+                c.required_permissions(admin_permissions)
+                    .guild_only(true)
+                    .exec(admin::rank)
+            })
+            .command("ranks", |c| {
+                c.required_permissions(admin_permissions)
+                    .guild_only(true)
+                    .exec(reconcile::command)
+            })
+            .command("thanks", |c| c.guild_only(true).exec(kudos::thanks))
+            .command("prestige", |c| c.guild_only(true).exec(prestige::command))
+            .command("config", |c| {
+                c.required_permissions(admin_permissions)
+                    .guild_only(true)
+                    .exec(messages::command)
+            })
+            .command("language", |c| c.exec(i18n::command))
+    );
 
-        if let Err(why) = client.start() {
-            error!("An error occurred while running the client: {:?}", why);
-        }
-    } else {
-        error!("Invalid configuration!");
+    if let Err(why) = client.start() {
+        error!("An error occurred while running the client: {:?}", why);
     }
 
     Ok(())
@@ -491,49 +1002,76 @@ CommandError> {
   Ok(())
 }
 
-const BLAST_ICON_URL: &str =
-    "https://cdn.discordapp.com/icons/506219319030448128/342d618bf1cb75d7ce71c44a0904b437.webp";
+/// what an embed about a member is written with: the guild's branding and
+/// templates, the reader's language, the member's leaderboard position and
+/// the embed's timestamp
+struct Rendering {
+    style: branding::Style,
+    templates: messages::Templates,
+    lang: i18n::Lang,
+    position: Option<usize>,
+    at: DateTime<FixedOffset>,
+}
+
+/// the placeholders every template can use
+fn rank_vars(user: &XPUser, ranks: &[Rank], position: Option<usize>, lang: i18n::Lang) -> messages::Vars {
+    let xp = user.meta.xp;
+    let mention = |rank: Option<&Rank>| rank.map(|r| format!("<@&{}>", r.role_id.0)).unwrap_or_default();
+    let next = ranks::next_rank(ranks, xp);
+    messages::Vars::new()
+        .set("user", format!("<@!{}>", user.user_id.0))
+        .set("rank", mention(ranks::rank_for(ranks, xp)))
+        .set("next_rank", mention(next))
+        .set("xp", i18n::number(lang, xp))
+        .set("xp_needed", i18n::number(lang, next.map(|r| r.required_xp - xp).unwrap_or(0.0)))
+        .set("level", ranks::level(ranks, xp))
+        .set("position", position.map(|p| p.to_string()).unwrap_or_default())
+}
 
+/// `crossed` are the ranks reached by this award, lowest first
 fn create_level_up_embed(
     user: XPUser,
-    ranks: Vec<Rank>,
-    at: DateTime<FixedOffset>,
+    ranks: &[Rank],
+    crossed: &[&Rank],
+    rendering: &Rendering,
     avatar_url: Option<String>,
 ) -> serenity::builder::CreateMessage {
-    fn make_description(user_id: String, new_rank: &Rank, rem: f64, next: Option<&Rank>) -> String {
-        if let Some(next_rank) = next {
-            format!("Congratulations <@!{}>, you have just leveled up to rank <@&{}>. You need **{:.3}** more XP to achieve rank <@&{}>.", user_id, new_rank.role_id.to_string(), rem, next_rank.role_id.to_string())
-        } else {
-            format!("Congratulations <@!{}>, you are at the max level!", user_id)
-        }
-    }
-
-    let ach: Vec<Rank> = ranks
-        .clone()
-        .into_iter()
-        .filter(|r| user.meta.xp >= r.required_xp)
-        .collect();
-    let rem: Vec<Rank> = ranks
-        .into_iter()
-        .filter(|r| user.meta.xp < r.required_xp)
+    let (style, templates, lang) = (&rendering.style, &rendering.templates, rendering.lang);
+    // the highest rank reached, whatever order the ranks are configured in
+    let new_rank = ranks::rank_for(ranks, user.meta.xp)
+        .or_else(|| crossed.last().cloned())
+        .expect("level up without a rank");
+    let passed: Vec<&Rank> = crossed
+        .iter()
+        .cloned()
+        .filter(|r| r.role_id != new_rank.role_id)
         .collect();
-
-    warn!("achieved: {:?}", ach);
-    warn!("remaining: {:?}", rem);
+    let next = ranks::next_rank(ranks, user.meta.xp);
+    let passing = if passed.is_empty() {
+        String::new()
+    } else {
+        let names = passed
+            .iter()
+            .map(|r| format!("<@&{}>", r.role_id.0))
+            .collect::<Vec<String>>()
+            .join(", ");
+        i18n::fill(lang, "levelup.passing", &[("ranks", names)])
+    };
+    let vars = rank_vars(&user, ranks, rendering.position, lang).set("passed", passing);
 
     serenity::builder::CreateMessage::default().embed(|mut e: serenity::builder::CreateEmbed| {
-        e = e
-            .author(|a| a.name("Blast — Level up!").icon_url(BLAST_ICON_URL))
-            .description(&*make_description(
-                user.user_id.0.to_string(),
-                &ach[ach.len() - 1],
-                rem.get(0)
-                    .map(|r| r.required_xp - user.meta.xp)
-                    .unwrap_or(0.0),
-                rem.get(0),
-            ))
-            .timestamp(&at)
-            .footer(|f| f.text(&*format!("You have {:.3} XP", user.meta.xp)));
+        e = style
+            .embed(e, i18n::text(lang, "title.levelup"))
+            .description(&*match (&new_rank.rewards.message, next) {
+                (Some(message), _) => rewards::fill(message, user.user_id, new_rank, user.meta.xp, lang),
+                (None, Some(_)) => templates.render("levelup", &vars),
+                (None, None) => templates.render("levelup_max", &vars),
+            })
+            .timestamp(&rendering.at)
+            .footer(|f| {
+                let own = i18n::fill(lang, "levelup.footer", &[("xp", i18n::number(lang, user.meta.xp))]);
+                f.text(&*style.footer_with(&*own))
+            });
         if let Some(a_url) = avatar_url {
             e = e.thumbnail(&*a_url);
         }
@@ -541,157 +1079,206 @@ fn create_level_up_embed(
     })
 }
 
+/// ranks `user` up after an award took them from `old` xp to the xp in
+/// `user`, on `track` or else on the main ranks. on a track `user` holds the
+/// track's view of the record. syncs the member's roles, then announces the
+/// ranks reached in `channel` and runs their rewards. ranks a requirement
+/// holds back don't count as reached, and locked members stay where they
+/// are. returns the posted level up
+fn rank_up(
+    state: &State,
+    guild: GuildId,
+    user: &XPUser,
+    old: f64,
+    track: Option<&tracks::Track>,
+    channel: ChannelId,
+    at: DateTime<FixedOffset>,
+) -> Option<Message> {
+    let mut member = guild.member(user.user_id).ok()?;
+    let ranks = track.map(|t| &t.ranks).unwrap_or_else(|| state.ranks_for(Some(guild)));
+    let now = Utc::now();
+    let eligible = ranks::eligible(ranks, &member, &user.meta, now);
+    ranks::sync_member_ranks(&mut member, ranks, state.rank_mode(guild), &user.meta);
+    let crossed = ranks::crossed(&eligible, old, user.meta.xp);
+    if crossed.is_empty() || holds::locked_rank(ranks, guild, &user.meta, now).is_some() {
+        return None;
+    }
+    let lang = state.lang(Some(guild), user.user_id);
+    let posted = match track {
+        Some(track) => {
+            tracks::announce(channel, user.user_id, &track.name, &crossed, lang);
+            None
+        }
+        None => channel
+            .send_message(|_| {
+                create_level_up_embed(
+                    user.clone(),
+                    &eligible,
+                    &crossed,
+                    &state.rendering(Some(guild), lang, user.meta.xp, at, messages::LEVEL_UP),
+                    member.user.read().avatar_url(),
+                )
+            })
+            .map_err(|why| warn!("Failed to post a level up: {:?}", why))
+            .ok(),
+    };
+    for rank in &crossed {
+        rewards::grant(guild, user.user_id, rank, user.meta.xp, Some(channel), lang);
+    }
+    posted
+}
+
+/// `blocked` explains the ranks a requirement still holds back
 fn create_info_embed(
     xp_user: XPUser,
     ranks: &Vec<Rank>,
-    at: DateTime<FixedOffset>,
-    myself: bool,
+    tracks: &[tracks::Track],
+    blocked: &[String],
+    settings: &Settings,
+    rendering: &Rendering,
     avatar: Option<String>,
 ) -> serenity::builder::CreateMessage {
-    if let Some(next) = xp_user.left(&ranks).get(0) {
-        if let Some(current) = xp_user.level(&ranks) {
-            let left_xp = next.required_xp - xp_user.meta.xp;
-            serenity::builder::CreateMessage::default().embed(|mut e| { 
-              e = e.author(|a| a.name("Blast — Statistics").icon_url(BLAST_ICON_URL)).description(format!("{} at rank <@&{}> with **{:.3}** XP. {} need **{:.3}** more XP to advance to rank <@&{}>.", {
-				if myself {
-					"You're currently".to_string()
-				} else {
-					format!("<@!{}> is", xp_user.user_id.0.to_string())
-				}
-			}, current.role_id.0.to_string(), xp_user.meta.xp, if myself {
-					"You"
-				} else {
-					"They"
-				}, left_xp, next.role_id.0.to_string())).timestamp(&at); 
-		if let Some(avatar_url) = avatar {
-			e = e.thumbnail(avatar_url);
-		}
-		e
-		})
-        } else {
-            let left_xp = next.required_xp - xp_user.meta.xp;
-            serenity::builder::CreateMessage::default().embed(|mut e: serenity::builder::CreateEmbed| { e = e.author(|a| a.name("Blast — Statistics").icon_url(BLAST_ICON_URL))
-		.description(format!("{} no rank and **{:.3}** XP. {} need **{:.3}** more XP to advance to rank <@&{}>.", {
-				if myself {
-					"You currently have".to_string()
-				} else {
-					format!("<@!{}> has", xp_user.user_id.0.to_string())
-				}
-			}, xp_user.meta.xp, {
-				if myself {
-					"You"
-				} else {
-					"They"
-				}
-			}, left_xp, next.role_id.0.to_string())).timestamp(&at); 
-		if let Some(avatar_url) = avatar {
-			e = e.thumbnail(avatar_url);
-		}
-		e})
+    let (style, templates, lang) = (&rendering.style, &rendering.templates, rendering.lang);
+    let vars = rank_vars(&xp_user, ranks, rendering.position, lang);
+    let description = match (
+        ranks::rank_for(ranks, xp_user.meta.xp),
+        ranks::next_rank(ranks, xp_user.meta.xp),
+    ) {
+        (_, None) => templates.render("stats_max", &vars),
+        (None, Some(_)) => templates.render("stats_unranked", &vars),
+        (Some(_), Some(_)) => templates.render("stats", &vars),
+    };
+    let allowance = match caps::remaining(&xp_user.meta, settings, Utc::now()) {
+        (None, None) => None,
+        (daily, weekly) => Some(
+            vec![daily.map(|d| ("stats.left_today", d)), weekly.map(|w| ("stats.left_week", w))]
+                .into_iter()
+                .filter_map(|x| x)
+                .map(|(key, left)| i18n::fill(lang, key, &[("xp", i18n::number(lang, left))]))
+                .collect::<Vec<String>>()
+                .join("\n"),
+        ),
+    };
+    serenity::builder::CreateMessage::default().embed(|mut e: serenity::builder::CreateEmbed| {
+        e = style
+            .embed(e, i18n::text(lang, "title.stats"))
+            .description(description)
+            .timestamp(&rendering.at);
+        if !blocked.is_empty() {
+            e = e.field(i18n::text(lang, "stats.requirements"), blocked.join("\n"), false);
         }
-    } else {
-        serenity::builder::CreateMessage::default().embed(
-            |mut e: serenity::builder::CreateEmbed| {
-                e = e
-                    .author(|a| a.name("Blast — Statistics").icon_url(BLAST_ICON_URL))
-                    .description(format!(
-                        "{} at the max rank <@&{}>, with **{:.3}** XP.",
-                        {
-                            if myself {
-                                "You're currently ".to_string()
-                            } else {
-                                format!("<@!{}> is", xp_user.user_id.0.to_string())
-                            }
-                        },
-                        xp_user
-                            .achieved(&ranks)
-                            .last()
-                            .unwrap()
-                            .role_id
-                            .0
-                            .to_string(),
-                        xp_user.meta.xp
-                    ))
-                    .timestamp(&at);
-                if let Some(avatar_url) = avatar {
-                    e = e.thumbnail(avatar_url);
+        if let Some(allowance) = allowance {
+            e = e.field(i18n::text(lang, "stats.allowance"), allowance, true);
+        }
+        let badges = achievements::badge_row(&settings.achievements, &xp_user.meta);
+        if !badges.is_empty() {
+            e = e.field(i18n::text(lang, "stats.badges"), badges, false);
+        }
+        if settings.kudos_xp.is_some() {
+            e = e.field(
+                i18n::text(lang, "stats.kudos"),
+                format!("**{}**", i18n::number(lang, xp_user.meta.kudos as f64)),
+                true,
+            );
+        }
+        if let Some(ref enabled) = settings.prestige {
+            let level = xp_user.meta.prestige;
+            let value = match prestige::tag(&xp_user.meta, settings) {
+                Some(tag) if enabled.multiplier > 0.0 => {
+                    let percent = ((prestige::multiplier(&xp_user.meta, settings) - 1.0) * 100.0).round();
+                    i18n::fill(
+                        lang,
+                        "stats.prestige_bonus",
+                        &[("tag", tag), ("level", level.to_string()), ("percent", i18n::number(lang, percent))],
+                    )
                 }
-                e
-            },
-        )
-    }
+                Some(tag) => i18n::fill(lang, "stats.prestige_level", &[("tag", tag), ("level", level.to_string())]),
+                None => i18n::text(lang, "stats.prestige_none").to_string(),
+            };
+            e = e.field(i18n::text(lang, "stats.prestige"), value, true);
+        }
+        for track in tracks {
+            let xp = xp_user.meta.track_xp(&track.key);
+            let value = match ranks::rank_for(&track.ranks, xp) {
+                Some(rank) => i18n::fill(
+                    lang,
+                    "stats.xp_rank",
+                    &[("xp", i18n::number(lang, xp)), ("rank", format!("<@&{}>", rank.role_id.0))],
+                ),
+                None => i18n::fill(lang, "stats.xp", &[("xp", i18n::number(lang, xp))]),
+            };
+            e = e.field(&*track.name, value, true);
+        }
+        if settings.season_length_days.is_some() {
+            e = e.field(
+                i18n::text(lang, "stats.lifetime"),
+                i18n::fill(lang, "stats.xp", &[("xp", i18n::number(lang, xp_user.meta.lifetime_xp()))]),
+                true,
+            );
+        }
+        if let Some(avatar_url) = avatar {
+            e = e.thumbnail(avatar_url);
+        }
+        e
+    })
 }
 
-/// create_leaderboard_embed assumes users is already sorted
+/// the longest description discord takes in an embed
+const DESCRIPTION_LIMIT: usize = 2048;
+
+/// the shown page of `board`, with the page count in the footer if it has more
 fn create_leaderboard_embed(
-    users: Vec<XPUser>,
-    ranks: Vec<Rank>,
-    at: DateTime<FixedOffset>,
-    cap: usize,
-) -> serenity::builder::CreateMessage {
-    fn reify_user(xp_user: &XPUser, ranks: Vec<Rank>) -> Result<ReifiedXPUser, serenity::Error> {
-        let user_id = xp_user.user_id;
-        let xp = xp_user.meta.xp;
-        let user = user_id.to_user()?;
-        Ok(ReifiedXPUser {
-            xp_user: xp_user.clone(),
-            username: user.name,
-            discriminator: user.discriminator,
-            level: {
-                let opt = ranks
-                    .iter()
-                    .enumerate()
-                    .filter(|&x| xp >= x.1.required_xp)
-                    .last();
-                if opt.is_some() {
-                    opt.unwrap().0 + 1
-                } else {
-                    0
-                }
-            },
-        })
+    e: serenity::builder::CreateEmbed,
+    board: &pages::Board,
+    settings: &Settings,
+    style: &branding::Style,
+    templates: &messages::Templates,
+) -> serenity::builder::CreateEmbed {
+    fn get_emoji(ind: usize) -> String {
+        match ind {
+            1 => "🥇",
+            2 => "🥈",
+            3 => "🥉",
+            _ => "▫",
+        }
+        .to_string()
     }
-    fn xpuser_to_str(dat: (usize, ReifiedXPUser)) -> String {
-        fn get_emoji(ind: usize) -> String {
-            match ind {
-                1 => "🥇",
-                2 => "🥈",
-                3 => "🥉",
-                _ => "▫",
-            }
-            .to_string()
+    let lang = board.lang;
+    let mut description = String::new();
+    for (ind, usr) in board.shown() {
+        let prestige = prestige::tag(&usr.meta, settings)
+            .map(|tag| format!(" {}", tag))
+            .unwrap_or_default();
+        let line = templates.render(
+            "leaderboard_line",
+            &messages::Vars::new()
+                .set("medal", get_emoji(ind))
+                .set("position", ind)
+                .set("user", format!("<@!{}>", usr.user_id.0))
+                .set("prestige", prestige)
+                .set("level", ranks::level(&board.ranks, usr.meta.xp))
+                .set("xp", i18n::number(lang, usr.meta.xp)),
+        );
+        // long templates lose the last lines rather than the whole embed
+        if description.len() + line.len() + 1 > DESCRIPTION_LIMIT {
+            break;
         }
-        let (ind, usr) = dat;
-        format!(
-            "{} - #{}. <@!{}> (level **{}**, **{:.3}** XP)",
-            get_emoji(ind),
-            ind,
-            usr.xp_user.user_id.0,
-            usr.level,
-            usr.xp_user.meta.xp
-        )
+        if !description.is_empty() {
+            description.push('\n');
+        }
+        description.push_str(&line);
     }
-    serenity::builder::CreateMessage::default().embed(|e: serenity::builder::CreateEmbed| {
-        e.author(|a| a.name("Blast — Leaderboard").icon_url(BLAST_ICON_URL))
-            .description({
-                let mut sorted = users.clone();
-                sorted.sort_by(|a, b| {
-                    let bxp = b.meta.xp;
-                    let axp = a.meta.xp;
-                    axp.partial_cmp(&bxp).unwrap_or(std::cmp::Ordering::Equal)
-                });
-                let user_strs: Vec<String> = sorted
-                    .iter()
-                    .rev()
-                    .take(if cap < 1 { 5 } else { cap })
-                    .map(|x| reify_user(x, ranks.clone())) // TODO: Maybe wanna rewrite to avoid clone
-                    .filter_map(Result::ok)
-                    .enumerate()
-                    .map(|x| (x.0 + 1, x.1)) // move level up by one for display
-                    .map(xpuser_to_str)
-                    .collect();
-                user_strs.join("\n")
-            })
-            .timestamp(&at)
-    })
+    let e = style
+        .embed(e, i18n::text(lang, "title.leaderboard"))
+        .description(description)
+        .timestamp(&board.at);
+    if board.pages() < 2 {
+        return e;
+    }
+    let page = i18n::fill(
+        lang,
+        "leaderboard.page",
+        &[("page", (board.page() + 1).to_string()), ("pages", board.pages().to_string())],
+    );
+    e.footer(|f| f.text(&*style.footer_with(&page)))
 }
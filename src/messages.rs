@@ -0,0 +1,270 @@
+//! the texts of the level up, stats and leaderboard embeds as templates with
+//! `{placeholders}`. the built-in defaults come in every language and can be
+//! overridden in `[messages]`, per guild in the config, and per guild with
+//! `/config message`, which wins
+
+use super::{i18n, Handler, QueryError, State};
+use log::error;
+use redis::Commands;
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::{channel::Message, id::GuildId};
+use std::collections::HashMap;
+
+/// every template, the defaults are `template.<name>` in the catalogs
+pub const NAMES: &[&str] = &[
+    "levelup",
+    "levelup_max",
+    "stats",
+    "stats_unranked",
+    "stats_max",
+    "leaderboard_line",
+];
+
+/// the templates a level up embed picks from
+pub const LEVEL_UP: &[&str] = &["levelup", "levelup_max"];
+/// the templates a `/stats` embed picks from
+pub const STATS: &[&str] = &["stats", "stats_unranked", "stats_max"];
+
+/// what templates may refer to
+pub const PLACEHOLDERS: &[&str] = &[
+    "user", "rank", "next_rank", "xp", "xp_needed", "level", "position", "passed", "medal", "prestige",
+];
+
+/// values for the placeholders, missing ones render empty
+#[derive(Debug, Clone, Default)]
+pub struct Vars(Vec<(&'static str, String)>);
+
+impl Vars {
+    pub fn new() -> Vars {
+        Vars::default()
+    }
+
+    pub fn set<V: ToString>(mut self, name: &'static str, value: V) -> Vars {
+        self.0.push((name, value.to_string()));
+        self
+    }
+}
+
+/// the templates of one guild, falling back to the defaults of the language
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    overrides: HashMap<String, String>,
+    lang: i18n::Lang,
+}
+
+impl Templates {
+    pub fn get(&self, key: &str) -> &str {
+        match self.overrides.get(key) {
+            Some(template) => template,
+            None if is_key(key) => i18n::text(self.lang, &*format!("template.{}", key)),
+            None => "",
+        }
+    }
+
+    pub fn render(&self, key: &str, vars: &Vars) -> String {
+        render(self.get(key), vars)
+    }
+
+    /// whether any of the `keys` templates shows `{placeholder}`
+    pub fn shows(&self, keys: &[&str], placeholder: &str) -> bool {
+        let placeholder = format!("{{{}}}", placeholder);
+        keys.iter().any(|key| self.get(key).contains(&*placeholder))
+    }
+}
+
+pub fn render(template: &str, vars: &Vars) -> String {
+    let mut out = template.to_string();
+    for name in PLACEHOLDERS {
+        let value = vars.0.iter().find(|(n, _)| n == name).map(|(_, v)| &**v).unwrap_or("");
+        out = out.replace(&*format!("{{{}}}", name), value);
+    }
+    out
+}
+
+/// checks the braces pair up and only known placeholders are used, the
+/// reason is given in `lang`
+pub fn validate(template: &str, lang: i18n::Lang) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err(i18n::text(lang, "invalid.empty").to_string());
+    }
+    let mut rest = template;
+    while let Some(open) = rest.find(|c: char| c == '{' || c == '}') {
+        if rest[open..].starts_with('}') {
+            return Err(i18n::text(lang, "invalid.close").to_string());
+        }
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => return Err(i18n::text(lang, "invalid.open").to_string()),
+        };
+        let name = &rest[open + 1..close];
+        if !PLACEHOLDERS.contains(&name) {
+            let names = PLACEHOLDERS
+                .iter()
+                .map(|p| format!("`{{{}}}`", p))
+                .collect::<Vec<String>>()
+                .join(", ");
+            return Err(i18n::fill(
+                lang,
+                "invalid.placeholder",
+                &[("name", name.to_string()), ("names", names)],
+            ));
+        }
+        rest = &rest[close + 1..];
+    }
+    Ok(())
+}
+
+pub fn is_key(key: &str) -> bool {
+    NAMES.contains(&key)
+}
+
+fn guild_key(guild: GuildId) -> String {
+    format!("messages:{}", guild.0)
+}
+
+/// the templates of `guild`: `/config message` over the guild's config over
+/// `[messages]` over the defaults in `lang`
+pub fn templates(state: &State, guild: Option<GuildId>, lang: i18n::Lang) -> Templates {
+    let mut map = state.config.messages.clone();
+    if let Some(guild) = guild {
+        if let Some(own) = state.config.messages_for(guild) {
+            map.extend(own.clone());
+        }
+        match stored(state, guild) {
+            Ok(stored) => map.extend(stored),
+            Err(why) => error!("Failed to load messages of guild {}: {:?}", guild, why),
+        }
+    }
+    Templates { overrides: map, lang }
+}
+
+fn stored(state: &State, guild: GuildId) -> Result<HashMap<String, String>, QueryError> {
+    let con = state.db.get_connection()?;
+    let stored: HashMap<String, String> = con.hgetall(guild_key(guild))?;
+    Ok(stored)
+}
+
+/// sample values for `/config preview`
+fn sample(msg: &Message, lang: i18n::Lang) -> Vars {
+    let passed = i18n::text(lang, "config.sample_passed").to_string();
+    Vars::new()
+        .set("user", format!("<@!{}>", msg.author.id.0))
+        .set("rank", i18n::text(lang, "config.sample_rank"))
+        .set("next_rank", i18n::text(lang, "config.sample_next_rank"))
+        .set("xp", i18n::number(lang, 1234.56))
+        .set("xp_needed", i18n::number(lang, 765.44))
+        .set("level", 2)
+        .set("position", 7)
+        .set("passed", i18n::fill(lang, "levelup.passing", &[("ranks", passed)]))
+        .set("medal", "🥇")
+        .set("prestige", " ⭐")
+}
+
+/// `/config message ...` and `/config preview ...`
+pub fn command(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let lock = ctx.data.lock();
+    let state = lock.get::<State>().expect("Failed to get State");
+    let lang = state.lang(msg.guild_id, msg.author.id);
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => {
+            msg.reply(i18n::text(lang, "not_in_guild"))?;
+            return Ok(());
+        }
+    };
+    let sub = args.single::<String>().unwrap_or_default();
+    let key = args.single::<String>().unwrap_or_default();
+    if !is_key(&*key) {
+        let names = i18n::fill(lang, "config.names", &[("names", NAMES.join(", "))]);
+        msg.reply(&*format!("{}\n{}", i18n::text(lang, "config.usage"), names))?;
+        return Ok(());
+    }
+    let template = args.rest().trim().to_string();
+    let invalid = |reason: String| i18n::fill(lang, "config.invalid", &[("reason", reason)]);
+    let failed = |why: QueryError| i18n::fill(lang, "error.save_message", &[("why", format!("{:?}", why))]);
+    let reply = match (&*sub, &*template) {
+        ("preview", "") => render(templates(state, Some(guild), lang).get(&key), &sample(msg, lang)),
+        ("preview", template) => match validate(template, lang) {
+            Err(reason) => invalid(reason),
+            Ok(()) => render(template, &sample(msg, lang)),
+        },
+        ("message", "") => format!("```{}```", templates(state, Some(guild), lang).get(&key)),
+        ("message", "reset") => match reset(state, guild, &key) {
+            Ok(()) => i18n::fill(lang, "config.reset", &[("name", key.clone())]),
+            Err(why) => failed(why),
+        },
+        ("message", template) => match validate(template, lang) {
+            Err(reason) => invalid(reason),
+            Ok(()) => match store(state, guild, &key, template) {
+                Ok(()) => i18n::fill(
+                    lang,
+                    "config.saved",
+                    &[("name", key.clone()), ("preview", render(template, &sample(msg, lang)))],
+                ),
+                Err(why) => failed(why),
+            },
+        },
+        _ => i18n::text(lang, "config.usage").to_string(),
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+fn store(state: &State, guild: GuildId, key: &str, template: &str) -> Result<(), QueryError> {
+    let con = state.db.get_connection()?;
+    let _: () = con.hset(guild_key(guild), key, template)?;
+    Ok(())
+}
+
+fn reset(state: &State, guild: GuildId, key: &str) -> Result<(), QueryError> {
+    let con = state.db.get_connection()?;
+    let _: () = con.hdel(guild_key(guild), key)?;
+    Ok(())
+}
+
+/// leaderboard position of a user, 1-based
+pub fn position(state: &State, xp: f64) -> Option<usize> {
+    Handler::get_users(&state.db)
+        .map(|users| users.iter().filter(|u| u.meta.xp > xp).count() + 1)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_are_validated() {
+        let lang = i18n::Lang::En;
+        let reason = |key: &str| -> Result<(), String> { Err(i18n::text(lang, key).to_string()) };
+        assert_eq!(validate("{user} reached {rank}!", lang), Ok(()));
+        assert_eq!(validate("no placeholders", lang), Ok(()));
+        assert_eq!(validate("", lang), reason("invalid.empty"));
+        assert_eq!(validate("  \n", lang), reason("invalid.empty"));
+        assert_eq!(validate("{user reached", lang), reason("invalid.open"));
+        assert_eq!(validate("user} reached", lang), reason("invalid.close"));
+        assert_eq!(validate("{user}} reached", lang), reason("invalid.close"));
+        match validate("{user} has {coins}", lang) {
+            Err(reason) => assert!(reason.contains("coins"), "{}", reason),
+            Ok(()) => panic!("an unknown placeholder passed"),
+        }
+        assert!(validate("{}", lang).is_err());
+    }
+
+    #[test]
+    fn templates_tell_which_placeholders_they_show() {
+        let mut overrides = HashMap::new();
+        overrides.insert("stats".to_string(), "{user} is #{position}".to_string());
+        overrides.insert("stats_max".to_string(), "{user} is done".to_string());
+        overrides.insert("stats_unranked".to_string(), "{user} has no rank".to_string());
+        let templates = Templates {
+            overrides,
+            lang: i18n::Lang::En,
+        };
+        assert!(templates.shows(STATS, "position"));
+        assert!(!templates.shows(&["stats_max", "stats_unranked"], "position"));
+        assert!(!templates.shows(&["not_a_template"], "position"));
+    }
+}
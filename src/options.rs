@@ -0,0 +1,181 @@
+//! settings from the command line and the environment. precedence is
+//! command line, then environment, then `config.toml`, then defaults.
+//!
+//! any config value can be set with `--set section.key=value` or
+//! `LEVELS__SECTION__KEY=value`; the common ones also have their own flags
+
+use std::fs;
+
+pub const USAGE: &str = "usage: levels [options]
+
+  --config <path>        config file (LEVELS_CONFIG, default config.toml)
+  --token <token>        discord bot token (DISCORD_TOKEN)
+  --token-file <path>    read the token from a file (DISCORD_TOKEN_FILE)
+  --redis-url <url>      storage.url (LEVELS_REDIS_URL)
+  --log-file <path>      logging.file (LEVELS_LOG_FILE)
+  --log-level <level>    logging.level (LEVELS_LOG_LEVEL)
+  --set <key>=<value>    any config value, e.g. --set xp.daily_cap=50 (LEVELS__XP__DAILY_CAP)
+  --help                 show this message";
+
+/// environment variables for the dedicated flags, and the key they set
+const ENV_KEYS: &[(&str, &str)] = &[
+    ("LEVELS_REDIS_URL", "storage.url"),
+    ("LEVELS_LOG_FILE", "logging.file"),
+    ("LEVELS_LOG_LEVEL", "logging.level"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub config_path: String,
+    pub token: Option<String>,
+    /// `(key, value)` pairs applied over the config file, later ones win
+    pub overrides: Vec<(String, String)>,
+}
+
+fn read_token(path: &str) -> Result<String, String> {
+    fs::read_to_string(path)
+        .map(|t| t.trim().to_string())
+        .map_err(|e| format!("could not read token file {}: {}", path, e))
+}
+
+impl Options {
+    /// `Ok(None)` means `--help` was given
+    pub fn parse<V, A>(vars: V, args: A) -> Result<Option<Options>, String>
+    where
+        V: IntoIterator<Item = (String, String)>,
+        A: IntoIterator<Item = String>,
+    {
+        let mut options = Options {
+            config_path: super::config::DEFAULT_PATH.to_string(),
+            token: None,
+            overrides: Vec::new(),
+        };
+
+        // environment first, so the command line can override it
+        let vars: Vec<(String, String)> = vars.into_iter().collect();
+        let var = |name: &str| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone());
+        if let Some(path) = var("LEVELS_CONFIG") {
+            options.config_path = path;
+        }
+        options.token = match (var("DISCORD_TOKEN"), var("DISCORD_TOKEN_FILE")) {
+            (Some(token), _) => Some(token),
+            (None, Some(path)) => Some(read_token(&*path)?),
+            (None, None) => None,
+        };
+        for (name, key) in ENV_KEYS {
+            if let Some(value) = var(name) {
+                options.overrides.push((key.to_string(), value));
+            }
+        }
+        for (name, value) in &vars {
+            if name.starts_with("LEVELS__") {
+                let key = name["LEVELS__".len()..].to_lowercase().replace("__", ".");
+                options.overrides.push((key, value.clone()));
+            }
+        }
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // accept both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.find('=') {
+                Some(i) if arg.starts_with("--") => (arg[..i].to_string(), Some(arg[i + 1..].to_string())),
+                _ => (arg.clone(), None),
+            };
+            if flag == "--help" || flag == "-h" {
+                return Ok(None);
+            }
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match &*flag {
+                "--config" => options.config_path = value()?,
+                "--token" => options.token = Some(value()?),
+                "--token-file" => options.token = Some(read_token(&*value()?)?),
+                "--redis-url" => options.overrides.push(("storage.url".to_string(), value()?)),
+                "--log-file" => options.overrides.push(("logging.file".to_string(), value()?)),
+                "--log-level" => options.overrides.push(("logging.level".to_string(), value()?)),
+                "--set" => {
+                    let pair = value()?;
+                    match pair.find('=') {
+                        Some(i) => options
+                            .overrides
+                            .push((pair[..i].to_string(), pair[i + 1..].to_string())),
+                        None => return Err(format!("--set expects key=value, got {:?}", pair)),
+                    }
+                }
+                other => return Err(format!("unknown option {:?}", other)),
+            }
+        }
+        Ok(Some(options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(vars: &[(&str, &str)], args: &[&str]) -> Result<Option<Options>, String> {
+        Options::parse(
+            vars.iter().map(|(k, v)| (k.to_string(), v.to_string())),
+            args.iter().map(|a| a.to_string()),
+        )
+    }
+
+    fn overrides(options: &Options) -> Vec<(&str, &str)> {
+        options.overrides.iter().map(|(k, v)| (&**k, &**v)).collect()
+    }
+
+    #[test]
+    fn flags_take_inline_or_separate_values() {
+        let options = parse(&[], &["--config=a.toml", "--log-level", "warn", "--set=xp.min=0.5"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.config_path, "a.toml");
+        assert_eq!(overrides(&options), vec![("logging.level", "warn"), ("xp.min", "0.5")]);
+        assert!(parse(&[], &["--token"]).is_err());
+        assert!(parse(&[], &["--set", "xp.min"]).is_err());
+        assert!(parse(&[], &["--nope"]).is_err());
+        assert!(parse(&[], &["--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn token_flags_win_over_the_environment() {
+        let path = std::env::temp_dir().join(format!("levels-token-{}", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        let file = path.to_str().unwrap();
+
+        let env = parse(&[("DISCORD_TOKEN", "from-env"), ("DISCORD_TOKEN_FILE", file)], &[]);
+        assert_eq!(env.unwrap().unwrap().token, Some("from-env".to_string()));
+        let env_file = parse(&[("DISCORD_TOKEN_FILE", file)], &[]);
+        assert_eq!(env_file.unwrap().unwrap().token, Some("from-file".to_string()));
+        let flag = parse(&[("DISCORD_TOKEN", "from-env")], &["--token-file", file]);
+        assert_eq!(flag.unwrap().unwrap().token, Some("from-file".to_string()));
+        let both = parse(&[("DISCORD_TOKEN_FILE", file)], &["--token=from-flag"]);
+        assert_eq!(both.unwrap().unwrap().token, Some("from-flag".to_string()));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn flags_override_the_environment_which_overrides_the_file() {
+        let vars = [
+            ("LEVELS_LOG_LEVEL", "info"),
+            ("LEVELS__XP__DAILY_CAP", "50"),
+            ("LEVELS_CONFIG", "env.toml"),
+        ];
+        let options = parse(&vars, &["--log-level=warn"]).unwrap().unwrap();
+        assert_eq!(options.config_path, "env.toml");
+        assert_eq!(
+            overrides(&options),
+            vec![("logging.level", "info"), ("xp.daily_cap", "50"), ("logging.level", "warn")]
+        );
+
+        let src = "[logging]\nfile = \"out.log\"\nlevel = \"error\"\n\n[xp]\nmin = 0.3\nmax = 0.5\ncooldown_secs = 5\ndaily_cap = 10.0\n\n[[guilds]]\nid = 1\n";
+        let loaded = crate::config::Config::parse(src, &options.overrides).unwrap();
+        assert_eq!(loaded.config.logging.level, "warn");
+        assert_eq!(loaded.config.xp.daily_cap, Some(50.0));
+    }
+}
@@ -0,0 +1,168 @@
+//! leaderboard pages: `/leaderboard` shows a page of members at a time, and
+//! ◀ ▶ reactions on the posted embed turn the pages for a while
+
+use super::{create_leaderboard_embed, i18n, messages, Rank, State, XPUser};
+use chrono::prelude::*;
+use log::warn;
+use serenity::client::Context;
+use serenity::model::channel::{Message, Reaction, ReactionType};
+use serenity::model::id::GuildId;
+use serenity::prelude::Mutex;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use typemap::ShareMap;
+
+/// members on a page unless `/leaderboard <n>` asks for another size
+pub const PAGE_SIZE: usize = 10;
+/// the most `/leaderboard <n>` puts on a page, more won't fit an embed
+pub const MAX_PAGE_SIZE: usize = 25;
+const PREVIOUS: &str = "◀";
+const NEXT: &str = "▶";
+/// how long after posting the reactions still turn the pages
+const TURNABLE: Duration = Duration::from_secs(5 * 60);
+
+/// parses the `page:<n>` leaderboard argument, pages count from one
+pub fn parse_arg(arg: &str) -> Option<usize> {
+    if arg.starts_with("page:") {
+        arg["page:".len()..].parse::<usize>().ok().filter(|&n| n > 0)
+    } else {
+        None
+    }
+}
+
+/// a posted leaderboard: the standings it was asked for and the page shown
+#[derive(Debug, Clone)]
+pub struct Board {
+    /// best first
+    users: Vec<XPUser>,
+    pub ranks: Vec<Rank>,
+    size: usize,
+    /// counted from zero
+    page: usize,
+    pub guild: Option<GuildId>,
+    pub lang: i18n::Lang,
+    pub at: DateTime<FixedOffset>,
+}
+
+impl Board {
+    /// `size` is capped at `MAX_PAGE_SIZE` and `page` at the last page
+    pub fn new(
+        mut users: Vec<XPUser>,
+        ranks: Vec<Rank>,
+        size: usize,
+        page: usize,
+        guild: Option<GuildId>,
+        lang: i18n::Lang,
+        at: DateTime<FixedOffset>,
+    ) -> Board {
+        users.sort_by(|a, b| b.meta.xp.partial_cmp(&a.meta.xp).unwrap_or(Ordering::Equal));
+        let mut board = Board {
+            users,
+            ranks,
+            size: size.max(1).min(MAX_PAGE_SIZE),
+            page: 0,
+            guild,
+            lang,
+            at,
+        };
+        board.page = page.min(board.pages() - 1);
+        board
+    }
+
+    pub fn pages(&self) -> usize {
+        ((self.users.len() + self.size - 1) / self.size).max(1)
+    }
+
+    pub fn page(&self) -> usize {
+        self.page
+    }
+
+    /// the users on the shown page with their positions
+    pub fn shown(&self) -> impl Iterator<Item = (usize, &XPUser)> {
+        self.users
+            .iter()
+            .enumerate()
+            .skip(self.page * self.size)
+            .take(self.size)
+            .map(|(i, user)| (i + 1, user))
+    }
+
+    /// moves a page forward or back, false if there is none that way
+    fn turn(&mut self, forward: bool) -> bool {
+        match (forward, self.page) {
+            (true, page) if page + 1 < self.pages() => self.page += 1,
+            (false, page) if page > 0 => self.page -= 1,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// lets the reactions turn the pages of `msg` for a while, if it has more
+/// than one. called without holding the data lock
+pub fn watch(data: Arc<Mutex<ShareMap>>, msg: &Message, board: Board) {
+    if board.pages() < 2 {
+        return;
+    }
+    {
+        let mut lock = data.lock();
+        let state = lock.get_mut::<State>().expect("Failed to get State");
+        state.boards.insert(msg.id, board);
+    }
+    for arrow in &[PREVIOUS, NEXT] {
+        if let Err(why) = msg.react(ReactionType::Unicode(arrow.to_string())) {
+            warn!("Failed to add the leaderboard reactions: {:?}", why);
+        }
+    }
+    let (channel, id) = (msg.channel_id, msg.id);
+    thread::spawn(move || {
+        thread::sleep(TURNABLE);
+        {
+            let mut lock = data.lock();
+            let state = lock.get_mut::<State>().expect("Failed to get State");
+            state.boards.remove(&id);
+        }
+        for arrow in &[PREVIOUS, NEXT] {
+            let _ = channel.delete_reaction(id, None, ReactionType::Unicode(arrow.to_string()));
+        }
+    });
+}
+
+/// turns the page of a leaderboard that is still being watched
+pub fn turn(ctx: &Context, reaction: &Reaction) {
+    if reaction.user_id == serenity::CACHE.read().user.id {
+        return;
+    }
+    // clients may send the arrows with an emoji variation selector
+    let forward = match reaction.emoji {
+        ReactionType::Unicode(ref emoji) => match emoji.trim_end_matches('\u{fe0f}') {
+            PREVIOUS => false,
+            NEXT => true,
+            _ => return,
+        },
+        _ => return,
+    };
+    let mut lock = ctx.data.lock();
+    let state = lock.get_mut::<State>().expect("Failed to get State");
+    let board = match state.boards.get_mut(&reaction.message_id) {
+        Some(board) => {
+            if !board.turn(forward) {
+                return;
+            }
+            board.clone()
+        }
+        None => return,
+    };
+    let state: &State = state;
+    let templates = messages::templates(state, board.guild, board.lang);
+    let edited = reaction.channel_id.edit_message(reaction.message_id, |m| {
+        m.embed(|e| create_leaderboard_embed(e, &board, &state.settings, &state.style(board.guild), &templates))
+    });
+    if let Err(why) = edited {
+        warn!("Failed to turn a leaderboard page: {:?}", why);
+    }
+    // so the same arrow turns again, only works with manage messages
+    let _ = reaction.delete();
+}
@@ -0,0 +1,118 @@
+//! `/prestige`: members at the top rank trade their xp for a prestige level,
+//! which shows as a badge, can come with a role and multiplies future xp
+
+use super::{i18n, ledger, ranks, Handler, QueryError, Settings, State, XPMeta};
+use log::info;
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::{
+    channel::Message,
+    id::{GuildId, RoleId, UserId},
+};
+
+#[derive(Debug, Clone)]
+pub struct Prestige {
+    pub badge: String,
+    /// granted on the first prestige
+    pub role: Option<RoleId>,
+    /// extra xp per prestige level, 0.1 gives +10% per level
+    pub multiplier: f64,
+    pub max: Option<u32>,
+}
+
+/// what xp awards are multiplied with for this member
+pub fn multiplier(meta: &XPMeta, settings: &Settings) -> f64 {
+    match settings.prestige {
+        Some(ref prestige) => 1.0 + prestige.multiplier * f64::from(meta.prestige),
+        None => 1.0,
+    }
+}
+
+/// the badge repeated or counted, for leaderboard lines and `/stats`
+pub fn tag(meta: &XPMeta, settings: &Settings) -> Option<String> {
+    match settings.prestige {
+        Some(ref prestige) if meta.prestige > 0 => Some(if meta.prestige <= 3 {
+            prestige.badge.repeat(meta.prestige as usize)
+        } else {
+            format!("{}×{}", prestige.badge, meta.prestige)
+        }),
+        _ => None,
+    }
+}
+
+pub fn command(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let lock = ctx.data.lock();
+    let state = lock.get::<State>().expect("Failed to get State");
+    let lang = state.lang(msg.guild_id, msg.author.id);
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => {
+            msg.reply(i18n::text(lang, "not_in_guild"))?;
+            return Ok(());
+        }
+    };
+    let confirmed = args.single::<String>().map(|a| a == "confirm").unwrap_or(false);
+    let reply = match prestige(state, guild, msg.author.id, confirmed, lang) {
+        Ok(reply) => reply,
+        Err(why) => i18n::fill(lang, "error.prestige", &[("why", format!("{:?}", why))]),
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+fn prestige(
+    state: &State,
+    guild: GuildId,
+    user: UserId,
+    confirmed: bool,
+    lang: i18n::Lang,
+) -> Result<String, QueryError> {
+    let settings = match state.settings.prestige {
+        Some(ref settings) => settings,
+        None => return Ok(i18n::text(lang, "prestige.disabled").to_string()),
+    };
+    let meta = Handler::get_user(&state.db, user)?;
+    let ranks = state.ranks_for(Some(guild));
+    let at_top = ranks::rank_for(ranks, meta.xp).is_some() && ranks::next_rank(ranks, meta.xp).is_none();
+    if !at_top {
+        return Ok(i18n::text(lang, "prestige.not_top").to_string());
+    }
+    if settings.max.map(|max| meta.prestige >= max).unwrap_or(false) {
+        return Ok(i18n::text(lang, "prestige.maxed").to_string());
+    }
+    let level = meta.prestige + 1;
+    if !confirmed {
+        let bonus = if settings.multiplier > 0.0 {
+            let percent = (settings.multiplier * f64::from(level) * 100.0).round();
+            i18n::fill(lang, "prestige.bonus", &[("percent", i18n::number(lang, percent))])
+        } else {
+            String::new()
+        };
+        return Ok(i18n::fill(
+            lang,
+            "prestige.confirm",
+            &[
+                ("xp", i18n::number(lang, meta.xp)),
+                ("level", level.to_string()),
+                ("badge", settings.badge.clone()),
+                ("bonus", bonus),
+            ],
+        ));
+    }
+
+    let (old, meta) = Handler::reset_season_xp(&state.db, user, 0.0, |meta| meta.prestige = level)?;
+    ledger::record(&state.db, user, -old, &*format!("prestige {}", level))?;
+    info!("{} reached prestige {}", user, level);
+    ranks::sync_user_ranks(guild, user, ranks, state.rank_mode(guild), &meta);
+    if let Some(role) = settings.role {
+        if let Ok(mut member) = guild.member(user) {
+            info!("adding prestige role: {:?}", member.add_role(role));
+        }
+    }
+    Ok(i18n::fill(
+        lang,
+        "prestige.done",
+        &[("level", level.to_string()), ("badge", settings.badge.clone())],
+    ))
+}
@@ -0,0 +1,336 @@
+//! resolving ranks from xp and keeping members' rank roles in sync with it.
+//! every code path that changes xp should end in `sync_member_ranks`
+
+use super::{holds, i18n, QueryError, Rank, XPMeta};
+use chrono::prelude::*;
+use log::{info, warn};
+use redis::Commands;
+use serenity::model::{
+    guild::Member,
+    id::{GuildId, RoleId, UserId},
+};
+
+/// whether members keep the roles of lower ranks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RankMode {
+    /// only the current rank's role
+    Exclusive,
+    /// the roles of every rank reached
+    Stacked,
+}
+
+impl Default for RankMode {
+    fn default() -> RankMode {
+        RankMode::Exclusive
+    }
+}
+
+/// conditions a rank can have on top of its xp. they're checked when a
+/// member would be promoted, members already holding the role keep it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Requirements {
+    /// days since joining the guild
+    #[serde(default)]
+    pub guild_days: Option<i64>,
+    /// days since the discord account was created
+    #[serde(default)]
+    pub account_days: Option<i64>,
+    #[serde(default)]
+    pub messages: Option<u64>,
+    /// a role the member has to hold already
+    #[serde(default)]
+    pub role: Option<RoleId>,
+}
+
+/// a requirement the member doesn't meet yet, with how far they are
+#[derive(Debug, Clone, PartialEq)]
+pub enum Missing {
+    GuildDays { need: i64, have: i64 },
+    AccountDays { need: i64, have: i64 },
+    Messages { need: u64, have: u64 },
+    Role(RoleId),
+}
+
+impl Missing {
+    pub fn describe(&self, lang: i18n::Lang) -> String {
+        let count = |n: i64| i18n::number(lang, n as f64);
+        match *self {
+            Missing::GuildDays { need, have } => {
+                i18n::fill(lang, "requirement.guild_days", &[("days", count(need)), ("have", count(have))])
+            }
+            Missing::AccountDays { need, have } => {
+                i18n::fill(lang, "requirement.account_days", &[("days", count(need)), ("have", count(have))])
+            }
+            Missing::Messages { need, have } => i18n::fill(
+                lang,
+                "requirement.messages",
+                &[("messages", count(need as i64)), ("have", count(have as i64))],
+            ),
+            Missing::Role(role) => i18n::fill(lang, "requirement.role", &[("role", format!("<@&{}>", role.0))]),
+        }
+    }
+}
+
+impl Requirements {
+    /// what the member still lacks, empty if they qualify
+    pub fn missing(&self, member: &Member, meta: &XPMeta, now: DateTime<Utc>) -> Vec<Missing> {
+        let mut missing = Vec::new();
+        if let Some(days) = self.guild_days {
+            let have = member
+                .joined_at
+                .map(|joined| now.signed_duration_since(joined).num_days())
+                .unwrap_or(0);
+            if have < days {
+                missing.push(Missing::GuildDays { need: days, have });
+            }
+        }
+        if let Some(days) = self.account_days {
+            let created = member.user.read().created_at();
+            let have = now.naive_utc().signed_duration_since(created).num_days();
+            if have < days {
+                missing.push(Missing::AccountDays { need: days, have });
+            }
+        }
+        if let Some(messages) = self.messages {
+            if meta.messages < messages {
+                missing.push(Missing::Messages {
+                    need: messages,
+                    have: meta.messages,
+                });
+            }
+        }
+        if let Some(role) = self.role {
+            if !member.roles.contains(&role) {
+                missing.push(Missing::Role(role));
+            }
+        }
+        missing
+    }
+}
+
+/// role changes made by a sync
+#[derive(Debug, Clone, Default)]
+pub struct RoleDiff {
+    pub added: Vec<RoleId>,
+    pub removed: Vec<RoleId>,
+}
+
+/// the highest rank reached with `xp`
+pub fn rank_for(ranks: &[Rank], xp: f64) -> Option<&Rank> {
+    ranks
+        .iter()
+        .filter(|r| xp >= r.required_xp)
+        .max_by(|a, b| {
+            a.required_xp
+                .partial_cmp(&b.required_xp)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
+
+/// how many ranks `xp` reaches, the level shown to members
+pub fn level(ranks: &[Rank], xp: f64) -> usize {
+    ranks.iter().filter(|r| xp >= r.required_xp).count()
+}
+
+/// the lowest rank not yet reached with `xp`
+pub fn next_rank(ranks: &[Rank], xp: f64) -> Option<&Rank> {
+    ranks
+        .iter()
+        .filter(|r| xp < r.required_xp)
+        .min_by(|a, b| {
+            a.required_xp
+                .partial_cmp(&b.required_xp)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
+
+/// every rank reached by going from `old` to `new` xp, lowest first. a
+/// single award can cross several ranks, and the config needn't be sorted
+pub fn crossed(ranks: &[Rank], old: f64, new: f64) -> Vec<&Rank> {
+    let mut crossed: Vec<&Rank> = ranks
+        .iter()
+        .filter(|r| old < r.required_xp && new >= r.required_xp)
+        .collect();
+    crossed.sort_by(|a, b| {
+        a.required_xp
+            .partial_cmp(&b.required_xp)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    crossed
+}
+
+/// the ranks the member may hold: those whose requirements they meet, and
+/// those they hold already
+pub fn eligible(ranks: &[Rank], member: &Member, meta: &XPMeta, now: DateTime<Utc>) -> Vec<Rank> {
+    ranks
+        .iter()
+        .filter(|r| member.roles.contains(&r.role_id) || r.requires.missing(member, meta, now).is_empty())
+        .cloned()
+        .collect()
+}
+
+/// ranks with enough xp that a requirement still holds back, lowest first,
+/// with what is missing. for `/stats`
+pub fn blocked<'a>(ranks: &'a [Rank], member: &Member, meta: &XPMeta, now: DateTime<Utc>) -> Vec<(&'a Rank, Vec<Missing>)> {
+    crossed(ranks, std::f64::NEG_INFINITY, meta.xp)
+        .into_iter()
+        .filter(|r| !member.roles.contains(&r.role_id))
+        .map(|r| (r, r.requires.missing(member, meta, now)))
+        .filter(|(_, missing)| !missing.is_empty())
+        .collect()
+}
+
+/// the rank roles a member with `xp` should hold
+pub fn roles_for(ranks: &[Rank], mode: RankMode, xp: f64) -> Vec<RoleId> {
+    match mode {
+        RankMode::Exclusive => rank_for(ranks, xp).map(|r| r.role_id).into_iter().collect(),
+        RankMode::Stacked => ranks
+            .iter()
+            .filter(|r| xp >= r.required_xp)
+            .map(|r| r.role_id)
+            .collect(),
+    }
+}
+
+/// adds missing and removes stale rank roles so the member matches their xp,
+/// promoting or demoting as needed. roles that aren't ranks are left alone.
+/// a rank lock overrides both the xp and the requirements
+pub fn sync_member_ranks(member: &mut Member, ranks: &[Rank], mode: RankMode, meta: &XPMeta) -> RoleDiff {
+    let now = Utc::now();
+    let wanted = match holds::locked_rank(ranks, member.guild_id, meta, now) {
+        Some(locked) => roles_for(ranks, mode, locked.required_xp),
+        None => roles_for(&eligible(ranks, member, meta, now), mode, meta.xp),
+    };
+    apply(member, ranks, &wanted)
+}
+
+/// `sync_member_ranks` for a member who came back. they met the requirements
+/// before leaving, and checking again would count `guild_days` from the new
+/// join, so only the xp and a rank lock decide
+pub fn restore_member_ranks(member: &mut Member, ranks: &[Rank], mode: RankMode, meta: &XPMeta) -> RoleDiff {
+    let wanted = match holds::locked_rank(ranks, member.guild_id, meta, Utc::now()) {
+        Some(locked) => roles_for(ranks, mode, locked.required_xp),
+        None => roles_for(ranks, mode, meta.xp),
+    };
+    apply(member, ranks, &wanted)
+}
+
+/// gives the member the `wanted` rank roles and takes the other rank roles
+fn apply(member: &mut Member, ranks: &[Rank], wanted: &[RoleId]) -> RoleDiff {
+    let diff = RoleDiff {
+        added: wanted
+            .iter()
+            .filter(|r| !member.roles.contains(r))
+            .cloned()
+            .collect(),
+        removed: ranks
+            .iter()
+            .map(|r| r.role_id)
+            .filter(|r| !wanted.contains(r) && member.roles.contains(r))
+            .collect(),
+    };
+    if !diff.removed.is_empty() {
+        info!("removing roles: {:?}", member.remove_roles(&diff.removed));
+    }
+    if !diff.added.is_empty() {
+        info!("adding roles: {:?}", member.add_roles(&diff.added));
+    }
+    diff
+}
+
+/// `sync_member_ranks` for a member that has to be looked up first
+pub fn sync_user_ranks(
+    guild: GuildId,
+    user: UserId,
+    ranks: &[Rank],
+    mode: RankMode,
+    meta: &XPMeta,
+) -> Option<RoleDiff> {
+    match guild.member(user) {
+        Ok(mut member) => Some(sync_member_ranks(&mut member, ranks, mode, meta)),
+        Err(why) => {
+            warn!("Could not get member {}: {:?}", user, why);
+            None
+        }
+    }
+}
+
+fn guild_key(guild: GuildId) -> String {
+    format!("ranks:{}", guild.0)
+}
+
+/// sorts by required xp, the order everything else expects
+pub fn sort(ranks: &mut Vec<Rank>) {
+    ranks.sort_by(|a, b| {
+        a.required_xp
+            .partial_cmp(&b.required_xp)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// ranks managed with `/rank`, `None` if the guild uses the config's
+pub fn load_guild_ranks(db: &redis::Client, guild: GuildId) -> Result<Option<Vec<Rank>>, QueryError> {
+    let con = db.get_connection()?;
+    let data: Option<String> = con.get(guild_key(guild))?;
+    Ok(match data {
+        Some(data) => Some(serde_json::from_str(&*data)?),
+        None => None,
+    })
+}
+
+pub fn save_guild_ranks(db: &redis::Client, guild: GuildId, ranks: &[Rank]) -> Result<(), QueryError> {
+    let con = db.get_connection()?;
+    let _: () = con.set(guild_key(guild), serde_json::to_string(ranks)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rank(role: u64, xp: f64) -> Rank {
+        Rank {
+            role_id: RoleId(role),
+            required_xp: xp,
+            name: None,
+            requires: Requirements::default(),
+            rewards: Default::default(),
+        }
+    }
+
+    /// deliberately out of order, like a hand-written config can be
+    fn unsorted() -> Vec<Rank> {
+        vec![rank(3, 300.0), rank(1, 100.0), rank(4, 1000.0), rank(2, 200.0)]
+    }
+
+    #[test]
+    fn highest_rank_ignores_config_order() {
+        let ranks = unsorted();
+        assert_eq!(rank_for(&ranks, 50.0), None);
+        assert_eq!(rank_for(&ranks, 250.0).map(|r| r.role_id), Some(RoleId(2)));
+        assert_eq!(rank_for(&ranks, 5000.0).map(|r| r.role_id), Some(RoleId(4)));
+        assert_eq!(next_rank(&ranks, 250.0).map(|r| r.role_id), Some(RoleId(3)));
+        assert_eq!(next_rank(&ranks, 5000.0), None);
+    }
+
+    #[test]
+    fn crossing_several_ranks_at_once() {
+        let ranks = unsorted();
+        let ids = |c: Vec<&Rank>| c.iter().map(|r| r.role_id.0).collect::<Vec<u64>>();
+        assert_eq!(ids(crossed(&ranks, 150.0, 350.0)), vec![2, 3]);
+        assert_eq!(ids(crossed(&ranks, 0.0, 1000.0)), vec![1, 2, 3, 4]);
+        assert_eq!(ids(crossed(&ranks, 100.0, 199.0)), Vec::<u64>::new());
+        // landing exactly on a threshold counts, starting on one doesn't
+        assert_eq!(ids(crossed(&ranks, 200.0, 300.0)), vec![3]);
+    }
+
+    #[test]
+    fn stacked_and_exclusive_roles() {
+        let ranks = unsorted();
+        assert_eq!(roles_for(&ranks, RankMode::Exclusive, 350.0), vec![RoleId(3)]);
+        let mut stacked = roles_for(&ranks, RankMode::Stacked, 350.0);
+        stacked.sort();
+        assert_eq!(stacked, vec![RoleId(1), RoleId(2), RoleId(3)]);
+    }
+}
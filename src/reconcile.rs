@@ -0,0 +1,202 @@
+//! bulk reconciliation of rank roles: walks every stored user and fixes the
+//! roles that drifted from their xp, e.g. while the bot was offline, after
+//! hand edits or after the ranks changed. covers the main ranks and every
+//! extra track. runs in the background at a pace
+//! that stays clear of discord's rate limits
+
+use super::{i18n, ranks, tracks, Handler, Rank, State, XPUser};
+use log::{error, info};
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::Mutex;
+use std::sync::{Arc, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+use typemap::ShareMap;
+
+/// pause after each member whose roles were changed
+const PACE: Duration = Duration::from_millis(750);
+/// how often the progress message is updated
+const PROGRESS_EVERY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checked: usize,
+    pub changed: usize,
+    pub added: usize,
+    pub removed: usize,
+    /// stored users that aren't in the guild (anymore)
+    pub absent: usize,
+}
+
+impl Report {
+    fn summary(&self, lang: i18n::Lang) -> String {
+        i18n::fill(
+            lang,
+            "sync.summary",
+            &[
+                ("checked", self.checked.to_string()),
+                ("changed", self.changed.to_string()),
+                ("added", self.added.to_string()),
+                ("removed", self.removed.to_string()),
+                ("absent", self.absent.to_string()),
+            ],
+        )
+    }
+}
+
+/// starts a sync of `guild` in the background, posting progress to `channel`
+/// if given, in the guild's language. returns false if one is already running
+/// for the guild
+pub fn start(data: Arc<Mutex<ShareMap>>, guild: GuildId, channel: Option<ChannelId>) -> bool {
+    let (users, ranks, tracks, mode, lang) = {
+        let mut lock = data.lock();
+        let state = lock.get_mut::<State>().expect("Failed to get State");
+        if !state.sync_jobs.insert(guild) {
+            return false;
+        }
+        (
+            Handler::get_users(&state.db),
+            state.ranks_for(Some(guild)).clone(),
+            state.tracks_for(Some(guild)).to_vec(),
+            state.rank_mode(guild),
+            state.config.language_for(guild).unwrap_or_default(),
+        )
+    };
+    thread::spawn(move || {
+        match users {
+            Ok(users) => {
+                let report = run(guild, &users, &ranks, &tracks, mode, channel, lang);
+                info!("Synced rank roles of guild {}: {:?}", guild, report);
+            }
+            Err(why) => {
+                error!("Could not sync guild {}: {:?}", guild, why);
+                if let Some(channel) = channel {
+                    let _ = channel.say(i18n::fill(lang, "error.grab_users", &[("why", format!("{:?}", why))]));
+                }
+            }
+        }
+        if let Some(state) = data.lock().get_mut::<State>() {
+            state.sync_jobs.remove(&guild);
+        }
+    });
+    true
+}
+
+/// starts a sync from a command, returning the reply to `user`
+pub fn begin(ctx: &Context, guild: GuildId, channel: ChannelId, user: UserId) -> String {
+    let lang = {
+        let lock = ctx.data.lock();
+        let state = lock.get::<State>().expect("Failed to get State");
+        state.lang(Some(guild), user)
+    };
+    if start(ctx.data.clone(), guild, Some(channel)) {
+        i18n::text(lang, "sync.started").to_string()
+    } else {
+        i18n::text(lang, "sync.running").to_string()
+    }
+}
+
+/// `/ranks sync`
+pub fn command(ctx: &mut Context, msg: &Message, args: Args) -> Result<(), CommandError> {
+    let mut args = args;
+    let guild = match msg.guild_id {
+        Some(guild) => guild,
+        None => {
+            msg.reply(i18n::text(i18n::Lang::default(), "not_in_guild"))?;
+            return Ok(());
+        }
+    };
+    let reply = match &*args.single::<String>().unwrap_or_default() {
+        "sync" => begin(ctx, guild, msg.channel_id, msg.author.id),
+        _ => {
+            let lock = ctx.data.lock();
+            let state = lock.get::<State>().expect("Failed to get State");
+            i18n::text(state.lang(Some(guild), msg.author.id), "sync.usage").to_string()
+        }
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
+
+static STARTUP: Once = Once::new();
+
+/// syncs the guilds with `sync_on_startup` once the cache is filled, only
+/// for the first connection so reconnects don't start it again
+pub fn on_startup(data: Arc<Mutex<ShareMap>>) {
+    STARTUP.call_once(|| {
+        let guilds = {
+            let lock = data.lock();
+            let state = lock.get::<State>().expect("Failed to get State");
+            state.config.startup_sync_guilds()
+        };
+        for guild in guilds {
+            info!("Syncing rank roles of guild {} on startup", guild);
+            start(data.clone(), guild, None);
+        }
+    });
+}
+
+fn run(
+    guild: GuildId,
+    users: &[XPUser],
+    ranks: &[Rank],
+    tracks: &[tracks::Track],
+    mode: ranks::RankMode,
+    channel: Option<ChannelId>,
+    lang: i18n::Lang,
+) -> Report {
+    let mut progress = channel.and_then(|c| {
+        c.say(i18n::fill(lang, "sync.begin", &[("count", users.len().to_string())]))
+            .ok()
+    });
+    let mut last_update = Instant::now();
+    let mut report = Report::default();
+    for user in users {
+        if last_update.elapsed() >= PROGRESS_EVERY {
+            last_update = Instant::now();
+            if let Some(ref mut msg) = progress {
+                let text = i18n::fill(
+                    lang,
+                    "sync.progress",
+                    &[
+                        ("done", report.checked.to_string()),
+                        ("total", users.len().to_string()),
+                        ("summary", report.summary(lang)),
+                    ],
+                );
+                let _ = msg.edit(|m| m.content(text));
+            }
+        }
+        report.checked += 1;
+        // the cache saves a request for every member that is already in sync
+        let cached = guild
+            .to_guild_cached()
+            .and_then(|g| g.read().members.get(&user.user_id).cloned());
+        let mut member = match cached.map(Ok).unwrap_or_else(|| guild.member(user.user_id)) {
+            Ok(member) => member,
+            Err(_) => {
+                report.absent += 1;
+                continue;
+            }
+        };
+        let mut diff = ranks::sync_member_ranks(&mut member, ranks, mode, &user.meta);
+        let on_tracks = tracks::sync_member(&mut member, tracks, mode, &user.meta);
+        diff.added.extend(on_tracks.added);
+        diff.removed.extend(on_tracks.removed);
+        if diff.added.is_empty() && diff.removed.is_empty() {
+            continue;
+        }
+        report.changed += 1;
+        report.added += diff.added.len();
+        report.removed += diff.removed.len();
+        thread::sleep(PACE);
+    }
+    if let Some(ref mut msg) = progress {
+        let text = i18n::fill(lang, "sync.done", &[("summary", report.summary(lang))]);
+        let _ = msg.edit(|m| m.content(text));
+    }
+    report
+}
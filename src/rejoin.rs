@@ -0,0 +1,53 @@
+//! giving returning members their rank roles back. discord drops every role
+//! when someone leaves, but their xp is still stored
+
+use super::{i18n, ranks, Handler, State};
+use chrono::prelude::*;
+use log::info;
+use serenity::model::{guild::Member, id::GuildId};
+
+/// re-applies the rank roles of a member that joined `guild`, and welcomes
+/// them back if the guild has a channel for it. xp is kept across guilds, so
+/// members new to this guild are left alone even if they have some
+pub fn restore(state: &State, guild: GuildId, member: &mut Member) {
+    let user = member.user.read().id;
+    let meta = match Handler::get_user(&state.db, user) {
+        Ok(meta) if meta.seen_in(guild) => meta,
+        _ => return,
+    };
+    let ranks = state.ranks_for(Some(guild));
+    let mode = state.rank_mode(guild);
+    let diff = ranks::restore_member_ranks(member, ranks, mode, &meta);
+    for track in state.tracks_for(Some(guild)) {
+        ranks::restore_member_ranks(member, &track.ranks, mode, &track.view(&meta));
+    }
+    info!("Restored roles of returning member {}: {:?}", user, diff);
+
+    let channel = match state.config.welcome_back_channel(guild) {
+        Some(channel) => channel,
+        None => return,
+    };
+    let lang = state.lang(Some(guild), user);
+    // the highest rank whose role was actually given back
+    let restored = match ranks.iter().rev().find(|r| diff.added.contains(&r.role_id)) {
+        Some(rank) => i18n::fill(lang, "welcome_back.restored", &[("rank", format!("<@&{}>", rank.role_id.0))]),
+        None => String::new(),
+    };
+    let text = i18n::fill(
+        lang,
+        "welcome_back.text",
+        &[
+            ("user", format!("<@!{}>", user.0)),
+            ("xp", i18n::number(lang, meta.xp)),
+            ("restored", restored),
+        ],
+    );
+    let style = state.style(Some(guild));
+    info!(
+        "{:?}",
+        channel.send_message(|m| m.embed(|e| style
+            .embed(e, i18n::text(lang, "title.welcome_back"))
+            .description(text)
+            .timestamp(&Utc::now())))
+    );
+}
@@ -0,0 +1,144 @@
+//! reloading the config without a restart, triggered by SIGHUP, a change to
+//! the config file or `/reload`. the new config is fully validated before it
+//! replaces the one in `State`, so a broken edit leaves the bot running as is
+
+use super::{config, i18n, State};
+use log::{error, info};
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError};
+use serenity::model::channel::Message;
+use serenity::prelude::Mutex;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, SystemTime};
+use std::{fs, thread};
+use typemap::ShareMap;
+
+/// how often the watcher looks at the signal flag and the file
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// loads and validates the config again and swaps it in, returning what changed
+pub fn reload(data: &Mutex<ShareMap>) -> Result<Vec<String>, config::ConfigError> {
+    let options = {
+        let lock = data.lock();
+        lock.get::<State>().expect("Failed to get State").options.clone()
+    };
+    let loaded = config::Config::load(&*options.config_path, &options.overrides)?;
+    let mut lock = data.lock();
+    let state = lock.get_mut::<State>().expect("Failed to get State");
+    let changes = describe(&state.config, &loaded.config);
+    state.config = loaded.config;
+    state.ranks = loaded.ranks;
+    state.settings = loaded.settings;
+    Ok(changes)
+}
+
+fn describe(old: &config::Config, new: &config::Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    for rank in &new.ranks {
+        match old.ranks.iter().find(|r| r.role == rank.role) {
+            None => changes.push(format!("added rank <@&{}> at {} XP", rank.role, rank.xp)),
+            Some(before) if before.xp != rank.xp => changes.push(format!(
+                "rank <@&{}> moved from {} to {} XP",
+                rank.role, before.xp, rank.xp
+            )),
+            Some(before) if before.name != rank.name => {
+                changes.push(format!("rank <@&{}> renamed to {:?}", rank.role, rank.name))
+            }
+            Some(before) if before != rank => {
+                changes.push(format!("rank <@&{}> requirements or rewards changed", rank.role))
+            }
+            Some(_) => {}
+        }
+    }
+    for rank in &old.ranks {
+        if !new.ranks.iter().any(|r| r.role == rank.role) {
+            changes.push(format!("removed rank <@&{}>", rank.role));
+        }
+    }
+    let sections = [
+        ("xp", old.xp != new.xp),
+        ("branding", old.branding != new.branding),
+        ("seasons", old.seasons != new.seasons),
+        ("kudos", old.kudos != new.kudos),
+        ("prestige", old.prestige != new.prestige),
+        ("cards", old.cards != new.cards),
+        ("achievements", old.achievements != new.achievements),
+        ("tracks", old.tracks != new.tracks),
+        ("messages", old.messages != new.messages),
+        ("guilds", old.guilds != new.guilds),
+    ];
+    for (name, changed) in &sections {
+        if *changed {
+            changes.push(format!("{} settings changed", name));
+        }
+    }
+    // these are only read at startup
+    let restart = [
+        ("storage", old.storage != new.storage),
+        ("logging", old.logging != new.logging),
+        ("admin", old.admin != new.admin),
+    ];
+    for (name, changed) in &restart {
+        if *changed {
+            changes.push(format!("{} settings changed (takes effect after a restart)", name));
+        }
+    }
+    changes
+}
+
+fn summary(changes: &[String]) -> String {
+    if changes.is_empty() {
+        "nothing changed".to_string()
+    } else {
+        changes.join("\n")
+    }
+}
+
+/// reloads on SIGHUP or when the config file's modification time changes
+pub fn watch(data: Arc<Mutex<ShareMap>>, path: String) -> Result<(), std::io::Error> {
+    let hangup = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::SIGHUP, Arc::clone(&hangup))?;
+    let modified = move || -> Option<SystemTime> { fs::metadata(&path).and_then(|m| m.modified()).ok() };
+    thread::spawn(move || {
+        let mut last_modified = modified();
+        loop {
+            thread::sleep(POLL_INTERVAL);
+            let now_modified = modified();
+            let trigger = if hangup.swap(false, Ordering::SeqCst) {
+                "SIGHUP"
+            } else if now_modified != last_modified {
+                "file change"
+            } else {
+                continue;
+            };
+            last_modified = now_modified;
+            match reload(&data) {
+                Ok(changes) => info!("Reloaded configuration ({}): {}", trigger, summary(&changes)),
+                Err(why) => error!("Not reloading configuration ({}): {}", trigger, why),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// the list of changes stays english, it is the same as in the log
+pub fn command(ctx: &mut Context, msg: &Message, _: Args) -> Result<(), CommandError> {
+    let result = reload(&ctx.data);
+    let lang = {
+        let lock = ctx.data.lock();
+        let state = lock.get::<State>().expect("Failed to get State");
+        state.lang(msg.guild_id, msg.author.id)
+    };
+    let reply = match result {
+        Ok(changes) => {
+            info!("Reloaded configuration (/reload): {}", summary(&changes));
+            i18n::fill(lang, "reload.done", &[("changes", summary(&changes))])
+        }
+        Err(why) => i18n::fill(lang, "reload.invalid", &[("why", why.to_string())]),
+    };
+    msg.reply(&*reply)?;
+    Ok(())
+}
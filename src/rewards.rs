@@ -0,0 +1,79 @@
+//! extra actions configured on a rank, run once when a member reaches it:
+//! a custom announcement, a dm, extra roles that aren't ranks, a post in a
+//! specific channel and a ping for staff
+
+use super::{i18n, Rank};
+use log::{info, warn};
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rewards {
+    /// replaces the level up text, see `fill` for the placeholders
+    #[serde(default)]
+    pub message: Option<String>,
+    /// sent to the member privately
+    #[serde(default)]
+    pub dm: Option<String>,
+    /// granted on top of the rank's role and kept on demotion
+    #[serde(default)]
+    pub roles: Vec<RoleId>,
+    /// where the rank is announced, next to the usual level up message
+    #[serde(default)]
+    pub channel: Option<ChannelId>,
+    /// pinged with the announcement
+    #[serde(default)]
+    pub ping: Option<RoleId>,
+}
+
+impl Rewards {
+    pub fn is_empty(&self) -> bool {
+        *self == Rewards::default()
+    }
+}
+
+/// fills `{user}`, `{rank}` and `{xp}` in a reward text
+pub fn fill(template: &str, user: UserId, rank: &Rank, xp: f64, lang: i18n::Lang) -> String {
+    template
+        .replace("{user}", &*format!("<@!{}>", user.0))
+        .replace("{rank}", &*format!("<@&{}>", rank.role_id.0))
+        .replace("{xp}", &*i18n::number(lang, xp))
+}
+
+/// runs the rewards of a rank the member just reached. `origin` is the
+/// channel the level up happened in, used for the staff ping if the rank has
+/// no channel of its own. texts are written in `lang`
+pub fn grant(guild: GuildId, user: UserId, rank: &Rank, xp: f64, origin: Option<ChannelId>, lang: i18n::Lang) {
+    let rewards = &rank.rewards;
+    if rewards.is_empty() {
+        return;
+    }
+    if !rewards.roles.is_empty() {
+        match guild.member(user) {
+            Ok(mut member) => info!("adding reward roles: {:?}", member.add_roles(&rewards.roles)),
+            Err(why) => warn!("Could not get member {}: {:?}", user, why),
+        }
+    }
+    if let Some(ref dm) = rewards.dm {
+        let sent = user
+            .create_dm_channel()
+            .and_then(|c| c.say(fill(dm, user, rank, xp, lang)));
+        if let Err(why) = sent {
+            warn!("Could not dm {}: {:?}", user, why);
+        }
+    }
+    let text = match rewards.message {
+        Some(ref message) => fill(message, user, rank, xp, lang),
+        None => fill(i18n::text(lang, "reward.reached"), user, rank, xp, lang),
+    };
+    let ping = rewards.ping.map(|role| format!("<@&{}> ", role.0));
+    let post = match (rewards.channel, ping) {
+        (Some(channel), ping) => Some((channel, format!("{}{}", ping.unwrap_or_default(), text))),
+        (None, Some(ping)) => origin.map(|channel| (channel, format!("{}{}", ping, text))),
+        (None, None) => None,
+    };
+    if let Some((channel, text)) = post {
+        if let Err(why) = channel.say(text) {
+            warn!("Failed to post rank reward: {:?}", why);
+        }
+    }
+}
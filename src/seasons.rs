@@ -0,0 +1,209 @@
+//! seasonal leaderboards. when a season is due to end its standings are
+//! archived, xp is (soft-)reset and the top members receive season roles.
+//! lifetime xp is never touched
+
+use super::{ledger, ranks, Handler, QueryError, Settings, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::{error, info, warn};
+use redis::Commands;
+use serenity::model::id::{GuildId, RoleId, UserId};
+
+const CURRENT_KEY: &str = "season:current";
+
+/// the season currently running
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Current {
+    pub number: u32,
+    pub started: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    pub user_id: UserId,
+    pub xp: f64,
+}
+
+/// an archived season, standings sorted best first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    pub number: u32,
+    pub started: DateTime<Utc>,
+    pub ended: DateTime<Utc>,
+    pub standings: Vec<Standing>,
+}
+
+impl Season {
+    /// the standings as users, for the leaderboard
+    pub fn users(&self) -> Vec<XPUser> {
+        self.standings
+            .iter()
+            .map(|s| XPUser {
+                user_id: s.user_id,
+                meta: XPMeta {
+                    xp: s.xp,
+                    ..XPMeta::new(self.ended)
+                },
+            })
+            .collect()
+    }
+}
+
+fn archive_key(number: u32) -> String {
+    format!("season:{}", number)
+}
+
+/// parses the `season:<n>` leaderboard argument
+pub fn parse_arg(arg: &str) -> Option<u32> {
+    if arg.starts_with("season:") {
+        arg["season:".len()..].parse::<u32>().ok()
+    } else {
+        None
+    }
+}
+
+/// the running season, starting the first one if there is none yet
+pub fn current(db: &redis::Client, settings: &Settings) -> Result<Current, QueryError> {
+    let con = db.get_connection()?;
+    let data: Option<String> = con.get(CURRENT_KEY)?;
+    if let Some(data) = data {
+        return Ok(serde_json::from_str(&*data)?);
+    }
+    let first = Current {
+        number: 1,
+        started: settings.season_start.unwrap_or_else(Utc::now),
+    };
+    let _: () = con.set(CURRENT_KEY, serde_json::to_string(&first)?)?;
+    Ok(first)
+}
+
+pub fn archived(db: &redis::Client, number: u32) -> Result<Option<Season>, QueryError> {
+    let con = db.get_connection()?;
+    let data: Option<String> = con.get(archive_key(number))?;
+    Ok(match data {
+        Some(data) => Some(serde_json::from_str(&*data)?),
+        None => None,
+    })
+}
+
+/// ends the running season if it is due. called periodically by the scheduler
+pub fn tick(state: &State) {
+    let length = match state.settings.season_length_days {
+        Some(days) => chrono::Duration::days(days),
+        None => return,
+    };
+    match current(&state.db, &state.settings) {
+        Ok(ref season) if Utc::now() >= season.started + length => {
+            if let Err(why) = rollover(state, season) {
+                error!("Failed to end season {}: {:?}", season.number, why);
+            }
+        }
+        Ok(_) => {}
+        Err(why) => error!("Failed to get current season: {:?}", why),
+    }
+}
+
+fn rollover(state: &State, season: &Current) -> Result<(), QueryError> {
+    let db = &state.db;
+    let guilds = state.config.guilds();
+    let now = Utc::now();
+    let mut users = Handler::get_users(db)?;
+    users.sort_by(|a, b| {
+        b.meta
+            .xp
+            .partial_cmp(&a.meta.xp)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let archive = Season {
+        number: season.number,
+        started: season.started,
+        ended: now,
+        standings: users
+            .iter()
+            .map(|u| Standing {
+                user_id: u.user_id,
+                xp: u.meta.xp,
+            })
+            .collect(),
+    };
+    let con = db.get_connection()?;
+    let _: () = con.set(archive_key(season.number), serde_json::to_string(&archive)?)?;
+    info!("Archived season {} ({} users)", season.number, users.len());
+
+    let next = Current {
+        number: season.number + 1,
+        started: now,
+    };
+    let _: () = con.set(CURRENT_KEY, serde_json::to_string(&next)?)?;
+
+    if let Some(keep) = state.settings.season_keep {
+        for user in &users {
+            let (old, meta) = Handler::reset_season_xp(db, user.user_id, keep, |_| {})?;
+            ledger::record(
+                db,
+                user.user_id,
+                meta.xp - old,
+                &*format!("season {} ended, kept {:.0}%", season.number, keep * 100.0),
+            )?;
+            for &guild in &guilds {
+                let mode = state.rank_mode(guild);
+                ranks::sync_user_ranks(guild, user.user_id, state.ranks_for(Some(guild)), mode, &meta);
+            }
+        }
+    }
+
+    for &(top, role) in &state.settings.season_rewards {
+        for &guild in &guilds {
+            award_role(guild, role, archive.standings.iter().take(top).map(|s| s.user_id));
+        }
+    }
+    Ok(())
+}
+
+/// moves a season role from last season's holders to the new ones
+fn award_role<I: Iterator<Item = UserId>>(guild: GuildId, role: RoleId, winners: I) {
+    let winners: Vec<UserId> = winners.collect();
+    if let Some(g) = guild.to_guild_cached() {
+        let holders: Vec<UserId> = g
+            .read()
+            .members
+            .values()
+            .filter(|m| m.roles.contains(&role))
+            .map(|m| m.user.read().id)
+            .filter(|id| !winners.contains(id))
+            .collect();
+        for id in holders {
+            if let Ok(mut member) = guild.member(id) {
+                info!("removing season role: {:?}", member.remove_role(role));
+            }
+        }
+    }
+    for id in winners {
+        match guild.member(id) {
+            Ok(mut member) => info!("adding season role: {:?}", member.add_role(role)),
+            Err(why) => warn!("Could not get member {}: {:?}", id, why),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lifetime_xp_survives_a_rollover() {
+        let mut meta = XPMeta::new(Utc::now());
+        meta.xp = 200.0;
+        meta.lifetime_xp = Some(500.0);
+        meta.keep_season_xp(0.5);
+        assert_eq!(meta.xp, 100.0);
+        assert_eq!(meta.lifetime_xp(), 500.0);
+
+        // from before seasons: all their xp so far is lifetime xp
+        let mut old = XPMeta::new(Utc::now());
+        old.xp = 80.0;
+        old.lifetime_xp = None;
+        old.keep_season_xp(0.0);
+        assert_eq!(old.xp, 0.0);
+        assert_eq!(old.lifetime_xp(), 80.0);
+    }
+}
@@ -0,0 +1,143 @@
+//! extra rank tracks next to the main xp, e.g. an event or helper ladder.
+//! each has its own xp counter in the user record, its own ranks and its own
+//! leaderboard (`/leaderboard track:<name>`), and is fed by the award sources
+//! the config lists for it. a guild can have tracks of its own in place of
+//! the global ones. there is no voice source: the bot doesn't follow voice
+//! channels, so a voice ladder needs voice time tracking first
+
+use super::{i18n, rank_up, ranks, Handler, QueryError, Rank, State, XPMeta, XPUser};
+use chrono::prelude::*;
+use log::{info, warn};
+use serenity::model::{
+    guild::Member,
+    id::{ChannelId, GuildId, UserId},
+};
+
+/// name of the built-in track, the one `/stats` and `/leaderboard` show by default
+pub const MAIN: &str = "main";
+
+/// where xp comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    Messages,
+    Kudos,
+}
+
+impl Source {
+    pub fn parse(name: &str) -> Option<Source> {
+        match name {
+            "messages" => Some(Source::Messages),
+            "kudos" => Some(Source::Kudos),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub name: String,
+    /// where the track's xp is kept in the user record
+    pub key: String,
+    pub sources: Vec<Source>,
+    /// sorted by required xp
+    pub ranks: Vec<Rank>,
+}
+
+impl Track {
+    /// `meta` with the xp of this track in place of the main xp, so the rank
+    /// helpers work on it unchanged
+    pub fn view(&self, meta: &XPMeta) -> XPMeta {
+        XPMeta {
+            xp: meta.track_xp(&self.key),
+            ..meta.clone()
+        }
+    }
+}
+
+/// parses `track:<name>` as given to `/leaderboard`
+pub fn parse_arg(arg: &str) -> Option<&str> {
+    if arg.starts_with("track:") {
+        Some(&arg["track:".len()..])
+    } else {
+        None
+    }
+}
+
+pub fn find<'a>(tracks: &'a [Track], name: &str) -> Option<&'a Track> {
+    tracks.iter().find(|t| t.name == name)
+}
+
+/// adds `amount` to every extra track fed by `source`, then ranks the member
+/// up on them like on the main ranks, announcing in `channel`. `amount` is
+/// after prestige and caps, see `caps::grantable`
+pub fn award(
+    state: &State,
+    guild: Option<GuildId>,
+    user: UserId,
+    source: Source,
+    amount: f64,
+    channel: ChannelId,
+    at: DateTime<FixedOffset>,
+) -> Result<(), QueryError> {
+    let fed: Vec<&Track> = state
+        .tracks_for(guild)
+        .iter()
+        .filter(|t| t.sources.contains(&source))
+        .collect();
+    if fed.is_empty() || amount <= 0.0 {
+        return Ok(());
+    }
+    let mut meta = Handler::get_user(&state.db, user)?;
+    let mut old = Vec::new();
+    for track in &fed {
+        let xp = meta.track_xp(&track.key);
+        meta.tracks.insert(track.key.clone(), xp + amount);
+        old.push(xp);
+    }
+    Handler::add_user(
+        &state.db,
+        XPUser {
+            user_id: user,
+            meta: meta.clone(),
+        },
+    )?;
+    if let Some(guild) = guild {
+        for (track, old) in fed.into_iter().zip(old) {
+            let viewed = XPUser {
+                user_id: user,
+                meta: track.view(&meta),
+            };
+            rank_up(state, guild, &viewed, old, Some(track), channel, at);
+        }
+    }
+    Ok(())
+}
+
+/// syncs the member's roles on every extra track
+pub fn sync_member(member: &mut Member, tracks: &[Track], mode: ranks::RankMode, meta: &XPMeta) -> ranks::RoleDiff {
+    let mut diff = ranks::RoleDiff::default();
+    for track in tracks {
+        let d = ranks::sync_member_ranks(member, &track.ranks, mode, &track.view(meta));
+        diff.added.extend(d.added);
+        diff.removed.extend(d.removed);
+    }
+    diff
+}
+
+/// posts the ranks reached on `track`
+pub fn announce(channel: ChannelId, user: UserId, track: &str, ranks: &[&Rank], lang: i18n::Lang) {
+    let roles = ranks
+        .iter()
+        .map(|r| format!("<@&{}>", r.role_id.0))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let text = i18n::fill(
+        lang,
+        "track.reached",
+        &[("user", format!("<@!{}>", user.0)), ("ranks", roles.clone()), ("track", track.to_string())],
+    );
+    match channel.say(text) {
+        Ok(_) => info!("{} reached {} on track {}", user, roles, track),
+        Err(why) => warn!("Failed to announce track rank: {:?}", why),
+    }
+}